@@ -1,6 +1,37 @@
+use crate::audio::{self, audio, decode_file, Sound, SoundError};
+use crate::config::{settings, SoundsSection};
+use crate::format::TimeStyle;
+use crate::locale::tr_with;
+use crate::pomodoro::Mode;
 use notify_rust::Notification;
-use rodio::{Decoder, OutputStream, Sink};
-use std::{io::Cursor, thread};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// What an alert is about, each kind can have its own sound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertKind {
+    WorkEnd,
+    BreakEnd,
+    LongBreakStart,
+    TimerEnd,
+    /// The configured `warn-before` time is left
+    Warning,
+}
+
+impl AlertKind {
+    /// The alert for a pomodoro phase of kind `ended` giving way to `next`
+    pub fn ending(ended: Mode, next: Mode) -> Self {
+        match (ended, next) {
+            (_, Mode::LongBreak) => Self::LongBreakStart,
+            (Mode::Work, _) => Self::WorkEnd,
+            // other phases ring for what comes after them
+            (Mode::Other, Mode::Break) => Self::WorkEnd,
+            _ => Self::BreakEnd,
+        }
+    }
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum AlertError {
@@ -19,71 +50,211 @@ pub fn notify_default(title: impl AsRef<str>, message: impl AsRef<str>) -> Resul
         .show()?;
     Ok(())
 }
-pub fn alert(title: impl Into<String>, message: impl Into<String>) {
-    let title = title.into();
-    let message = message.into();
-    thread::spawn(move || {
-        notify_default(title, message).unwrap();
-        play_bell().unwrap();
+
+/// Loudest volume in percent, above 100 makes sounds louder than recorded
+pub const MAX_VOLUME: u16 = 200;
+const VOLUME_STEP: u16 = 10;
+/// How long the footer shows the volume after it changed
+const VOLUME_SHOWN_FOR: Duration = Duration::from_secs(2);
+
+/// Master volume in percent and when it last changed
+static VOLUME: Mutex<Option<(u16, Option<Instant>)>> = Mutex::new(None);
+
+fn with_volume<T>(f: impl FnOnce(&mut (u16, Option<Instant>)) -> T) -> T {
+    let mut volume = VOLUME
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(volume.get_or_insert_with(|| (settings().volume, None)))
+}
+
+/// The master volume in percent
+pub fn volume() -> u16 {
+    with_volume(|(volume, _)| *volume)
+}
+
+/// Turns the master volume up or down a step
+pub fn change_volume(up: bool) {
+    with_volume(|(volume, changed)| {
+        *volume = if up {
+            (*volume + VOLUME_STEP).min(MAX_VOLUME)
+        } else {
+            volume.saturating_sub(VOLUME_STEP)
+        };
+        *changed = Some(Instant::now());
     });
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct Alerter(bool);
+/// `Volume 80%` for a little while after the volume changed
+pub fn volume_notice() -> Option<String> {
+    with_volume(|(volume, changed)| {
+        changed
+            .filter(|changed| changed.elapsed() < VOLUME_SHOWN_FOR)
+            .map(|_| tr_with("volume", &[("volume", volume)]))
+    })
+}
+
+// there is nobody to tell about failures from the notification thread, and
+// a missing notification daemon shouldn't bring porsmo down, sound failures
+// come back from the audio worker
+
+/// Notifications still being sent, see [`finish`]
+static SENDING: Mutex<Vec<JoinHandle<Result<(), AlertError>>>> = Mutex::new(Vec::new());
+
+/// How long [`finish`] waits for the sounds still playing
+const FINISH_TIMEOUT: Duration = Duration::from_secs(5);
 
-impl Default for Alerter {
-    fn default() -> Self {
-        Self(false)
+pub fn alert(title: impl Into<String>, message: impl Into<String>, kind: AlertKind) {
+    if settings().notify {
+        let (title, message) = (title.into(), message.into());
+        let handle = thread::spawn(move || notify_default(title, message));
+        let mut sending = SENDING
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        sending.retain(|handle| !handle.is_finished());
+        sending.push(handle);
     }
+    ring(kind, 0);
 }
 
-impl Alerter {
-    pub fn alert_once(&mut self, title: impl Into<String>, message: impl Into<String>) {
-        if !self.0 {
-            self.0 = true;
-            alert(title, message);
-        }
+/// Waits for the notifications still being sent and the sounds still
+/// playing, so an alert right before exiting isn't cut off
+pub fn finish() {
+    let sending = std::mem::take(
+        &mut *SENDING
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()),
+    );
+    for handle in sending {
+        let _ = handle.join();
     }
+    audio::drain(FINISH_TIMEOUT);
+}
 
-    pub fn reset(&mut self) {
-        self.0 = false;
+/// Plays the sound for `kind`, `repeat` counts how often the same alarm
+/// already rang before
+fn ring(kind: AlertKind, repeat: u32) {
+    let settings = settings();
+    if !settings.bell {
+        return;
     }
+    let alarm = &settings.alarm;
+    let urgent = repeat >= alarm.urgent_after.unwrap_or(1);
+    let file = match &settings.sounds.urgent {
+        Some(urgent_sound) if urgent && repeat > 0 => Some(urgent_sound.clone()),
+        _ => settings.sounds.get(kind).cloned(),
+    };
+    let louder = alarm.louder.unwrap_or(0).saturating_mul(repeat as u16);
+    let event_volume = settings.volumes.get(kind).saturating_add(louder);
+    let volume = volume() as f32 / 100.0 * event_volume.min(MAX_VOLUME) as f32 / 100.0;
+    audio().play(Sound {
+        file,
+        volume,
+        fade_in: settings.fade_in.unwrap_or_default(),
+    });
 }
 
-#[derive(Debug, thiserror::Error)]
-pub enum SoundError {
-    #[error(transparent)]
-    StreamError(#[from] rodio::StreamError),
-
-    #[error(transparent)]
-    DevicesError(#[from] rodio::DevicesError),
+/// Repetitions of an unacknowledged alarm when `alarm.every` is set
+/// without a `limit`
+const DEFAULT_REPEAT_LIMIT: u32 = 10;
 
-    #[error(transparent)]
-    DecoderError(#[from] rodio::decoder::DecoderError),
+/// An alarm that went off and may ring again until acknowledged
+#[derive(Debug, Clone, Copy)]
+struct Alarm {
+    kind: AlertKind,
+    rang_at: Instant,
+    repeats: u32,
+    acknowledged: bool,
+}
 
-    #[error("No devices found")]
-    NoDevice,
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Alerter {
+    alarm: Option<Alarm>,
+    warned: bool,
 }
 
-impl From<rodio::PlayError> for SoundError {
-    fn from(err: rodio::PlayError) -> Self {
-        match err {
-            rodio::PlayError::NoDevice => Self::NoDevice,
-            rodio::PlayError::DecoderError(e) => Self::DecoderError(e),
+impl Alerter {
+    /// Alerts the first time it's called, later calls ring the sound again
+    /// as the configured alarm policy asks until [`Alerter::acknowledge`]
+    pub fn alarm(&mut self, title: impl Into<String>, message: impl Into<String>, kind: AlertKind) {
+        let Some(alarm) = &mut self.alarm else {
+            alert(title, message, kind);
+            self.alarm = Some(Alarm {
+                kind,
+                rang_at: Instant::now(),
+                repeats: 0,
+                acknowledged: false,
+            });
+            return;
+        };
+        let policy = &settings().alarm;
+        let Some(every) = policy.every else {
+            return;
+        };
+        let limit = policy.limit.unwrap_or(DEFAULT_REPEAT_LIMIT);
+        if alarm.acknowledged || alarm.repeats >= limit || alarm.rang_at.elapsed() < every {
+            return;
+        }
+        alarm.repeats += 1;
+        alarm.rang_at = Instant::now();
+        ring(alarm.kind, alarm.repeats);
+    }
+
+    /// Silences the alarm and stops it from ringing again, until the next
+    /// one goes off
+    pub fn acknowledge(&mut self) {
+        if let Some(alarm) = &mut self.alarm {
+            alarm.acknowledged = true;
+            audio().stop();
         }
     }
+
+    /// Warns once `time_left` drops to the configured `warn-before`, unless
+    /// the whole `target` is shorter than that
+    pub fn warn_once(&mut self, title: impl Into<String>, time_left: Duration, target: Duration) {
+        let Some(warn_before) = settings().warn_before else {
+            return;
+        };
+        if !self.warned && time_left <= warn_before && warn_before < target {
+            self.warned = true;
+            let message = TimeStyle::Human.countdown(time_left.max(Duration::from_secs(1)));
+            alert(title, message, AlertKind::Warning);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
 }
 
-pub fn play_bell() -> Result<(), SoundError> {
-    let (_stream, stream_handle) = OutputStream::try_default()?;
+/// `~/` at the start of `path` replaced with the home directory
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
 
-    // let volume = 0.5;
-    let audio = Decoder::new(Cursor::new(include_bytes!("notify_end.wav")))?;
-    Sink::try_new(&stream_handle).map(|sink| {
-        sink.append(audio);
-        // sink.set_volume(volume);
-        sink.sleep_until_end();
-    })?;
+/// Makes sure every configured sound file can be decoded, the ones that
+/// can't are dropped so their alerts fall back to the bell. Returns the
+/// dropped files with the reason
+pub fn check_sounds(sounds: &mut SoundsSection) -> Vec<(PathBuf, SoundError)> {
+    let mut dropped = Vec::new();
+    for file in sounds.files_mut() {
+        let Some(path) = file.take().map(|path| expand_home(&path)) else {
+            continue;
+        };
+        match decode_file(&path) {
+            Ok(_) => *file = Some(path),
+            Err(error) => dropped.push((path, error)),
+        }
+    }
+    dropped
+}
 
-    Ok(())
+/// The warning shown for a sound file [`check_sounds`] dropped
+pub fn fallback_warning(path: &Path, error: &SoundError) -> String {
+    tr_with(
+        "sound-fallback",
+        &[("file", &path.display()), ("error", error)],
+    )
 }
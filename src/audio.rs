@@ -0,0 +1,323 @@
+use crate::config::settings;
+use crate::locale::tr_with;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long the footer shows an audio error
+const ERROR_SHOWN_FOR: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+pub enum SoundError {
+    #[error(transparent)]
+    StreamError(#[from] rodio::StreamError),
+
+    #[error(transparent)]
+    DevicesError(#[from] rodio::DevicesError),
+
+    #[error(transparent)]
+    DecoderError(#[from] rodio::decoder::DecoderError),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("No devices found")]
+    NoDevice,
+}
+
+impl From<rodio::PlayError> for SoundError {
+    fn from(err: rodio::PlayError) -> Self {
+        match err {
+            rodio::PlayError::NoDevice => Self::NoDevice,
+            rodio::PlayError::DecoderError(e) => Self::DecoderError(e),
+        }
+    }
+}
+
+/// Where sounds go
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AudioBackend {
+    /// The default output device
+    #[default]
+    System,
+    /// Nowhere, for machines without a sound card
+    Null,
+}
+
+/// A sound to play, the built-in bell when there is no file
+#[derive(Clone, Debug)]
+pub struct Sound {
+    pub file: Option<PathBuf>,
+    /// Factor where 1.0 leaves the sound as recorded
+    pub volume: f32,
+    pub fade_in: Duration,
+}
+
+enum AudioCommand {
+    /// Plays the sound after the ones already queued
+    Play(Sound),
+    /// Cancels the playing sound and everything queued
+    Stop,
+    /// Replies with the number of sounds that were queued once they finished
+    Drain(Sender<usize>),
+}
+
+trait Backend {
+    fn play(&mut self, sound: &Sound) -> Result<(), SoundError>;
+    fn stop(&mut self);
+    /// Waits for the queued sounds to finish, returns how many there were
+    fn drain(&mut self) -> usize;
+}
+
+/// Plays nothing, sounds count as queued until drained or stopped
+#[derive(Default)]
+struct NullBackend {
+    queued: usize,
+}
+
+impl Backend for NullBackend {
+    fn play(&mut self, _: &Sound) -> Result<(), SoundError> {
+        self.queued += 1;
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.queued = 0;
+    }
+
+    fn drain(&mut self) -> usize {
+        std::mem::take(&mut self.queued)
+    }
+}
+
+/// Plays through rodio, keeping the output stream open between sounds
+#[derive(Default)]
+struct RodioBackend {
+    output: Option<(OutputStream, OutputStreamHandle, Sink)>,
+}
+
+impl RodioBackend {
+    /// The sink of the open stream, opening it first if needed
+    fn sink(&mut self) -> Result<&Sink, SoundError> {
+        let (stream, handle, sink) = match self.output.take() {
+            Some(output) => output,
+            None => {
+                let (stream, handle) = OutputStream::try_default()?;
+                let sink = Sink::try_new(&handle)?;
+                (stream, handle, sink)
+            }
+        };
+        Ok(&self.output.insert((stream, handle, sink)).2)
+    }
+}
+
+type BoxedSource = Box<dyn Source<Item = f32> + Send>;
+
+fn bell() -> BoxedSource {
+    let bell = Decoder::new(Cursor::new(include_bytes!("notify_end.wav")))
+        .expect("the built-in bell is a valid wav");
+    Box::new(bell.convert_samples())
+}
+
+pub(crate) fn decode_file(path: &Path) -> Result<Decoder<BufReader<File>>, SoundError> {
+    Ok(Decoder::new(BufReader::new(File::open(path)?))?)
+}
+
+impl Backend for RodioBackend {
+    /// Falls back to the bell when the file can't be decoded, the error is
+    /// still returned so it can be shown
+    fn play(&mut self, sound: &Sound) -> Result<(), SoundError> {
+        let (source, decoded) = match &sound.file {
+            Some(path) => match decode_file(path) {
+                Ok(source) => (Box::new(source.convert_samples()) as BoxedSource, Ok(())),
+                Err(error) => (bell(), Err(error)),
+            },
+            None => (bell(), Ok(())),
+        };
+        let sink = match self.sink() {
+            Ok(sink) => sink,
+            Err(error) => {
+                // the device may be back by the next sound
+                self.output = None;
+                return Err(error);
+            }
+        };
+        sink.append(source.amplify(sound.volume).fade_in(sound.fade_in));
+        decoded
+    }
+
+    fn stop(&mut self) {
+        if let Some((_, handle, sink)) = &mut self.output {
+            sink.stop();
+            match Sink::try_new(handle) {
+                Ok(new) => *sink = new,
+                Err(_) => self.output = None,
+            }
+        }
+    }
+
+    fn drain(&mut self) -> usize {
+        let Some((_, _, sink)) = &self.output else {
+            return 0;
+        };
+        let queued = sink.len();
+        sink.sleep_until_end();
+        queued
+    }
+}
+
+/// Handle to the audio worker thread, which owns the output device
+pub struct Audio {
+    commands: Sender<AudioCommand>,
+    errors: Mutex<Receiver<SoundError>>,
+    /// The latest error and when it came in
+    shown_error: Mutex<Option<(String, Instant)>>,
+}
+
+impl Audio {
+    /// Starts the worker, the system backend opens the device right away
+    /// so the first alert doesn't wait for it
+    pub fn start(backend: AudioBackend) -> Self {
+        let (commands, command_rx) = mpsc::channel();
+        let (error_tx, errors) = mpsc::channel();
+        thread::spawn(move || {
+            // rodio's output stream can't move between threads, so the
+            // backend is made here
+            let mut backend: Box<dyn Backend> = match backend {
+                AudioBackend::System => {
+                    let mut rodio = RodioBackend::default();
+                    if let Err(error) = rodio.sink() {
+                        let _ = error_tx.send(error);
+                    }
+                    Box::new(rodio)
+                }
+                AudioBackend::Null => Box::new(NullBackend::default()),
+            };
+            for command in command_rx {
+                let result = match command {
+                    AudioCommand::Play(sound) => backend.play(&sound),
+                    AudioCommand::Stop => {
+                        backend.stop();
+                        Ok(())
+                    }
+                    AudioCommand::Drain(done) => {
+                        let _ = done.send(backend.drain());
+                        Ok(())
+                    }
+                };
+                if let Err(error) = result {
+                    let _ = error_tx.send(error);
+                }
+            }
+        });
+        Self {
+            commands,
+            errors: Mutex::new(errors),
+            shown_error: Mutex::new(None),
+        }
+    }
+
+    pub fn play(&self, sound: Sound) {
+        let _ = self.commands.send(AudioCommand::Play(sound));
+    }
+
+    pub fn stop(&self) {
+        let _ = self.commands.send(AudioCommand::Stop);
+    }
+
+    /// Waits up to `timeout` for the queued sounds to finish, returns how
+    /// many there were or `None` if they didn't finish in time
+    pub fn drain(&self, timeout: Duration) -> Option<usize> {
+        let (done, finished) = mpsc::channel();
+        self.commands.send(AudioCommand::Drain(done)).ok()?;
+        finished.recv_timeout(timeout).ok()
+    }
+
+    /// The latest error from the worker, for a few seconds after it happened
+    pub fn error_notice(&self) -> Option<String> {
+        let mut shown = self
+            .shown_error
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let errors = self
+            .errors
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(error) = errors.try_iter().last() {
+            let message = tr_with("sound-error", &[("error", &error)]);
+            *shown = Some((message, Instant::now()));
+        }
+        shown
+            .as_ref()
+            .filter(|(_, at)| at.elapsed() < ERROR_SHOWN_FOR)
+            .map(|(message, _)| message.clone())
+    }
+}
+
+static AUDIO: OnceLock<Audio> = OnceLock::new();
+
+pub fn install(audio: Audio) {
+    let _ = AUDIO.set(audio);
+}
+
+/// The installed audio worker, started with the configured backend if
+/// there is none yet
+pub fn audio() -> &'static Audio {
+    AUDIO.get_or_init(|| Audio::start(settings().audio))
+}
+
+/// [`Audio::error_notice`] of the installed worker, without starting one
+pub fn error_notice() -> Option<String> {
+    AUDIO.get().and_then(Audio::error_notice)
+}
+
+/// [`Audio::drain`] of the installed worker, without starting one
+pub fn drain(timeout: Duration) {
+    if let Some(audio) = AUDIO.get() {
+        audio.drain(timeout);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WAIT: Duration = Duration::from_secs(5);
+
+    fn sound() -> Sound {
+        Sound {
+            file: None,
+            volume: 1.0,
+            fade_in: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn sounds_queue_until_drained() {
+        let audio = Audio::start(AudioBackend::Null);
+        for _ in 0..3 {
+            audio.play(sound());
+        }
+        assert_eq!(audio.drain(WAIT), Some(3));
+        assert_eq!(audio.drain(WAIT), Some(0));
+        assert_eq!(audio.error_notice(), None);
+    }
+
+    #[test]
+    fn stop_cancels_the_queue() {
+        let audio = Audio::start(AudioBackend::Null);
+        audio.play(sound());
+        audio.play(sound());
+        audio.stop();
+        audio.play(sound());
+        assert_eq!(audio.drain(WAIT), Some(1));
+        assert_eq!(audio.error_notice(), None);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::config::settings;
+use crate::layout::text_width;
+use crate::theme::Borders;
+use crossterm::terminal;
+
+const HEIGHT: usize = 5;
+/// Fewest terminal rows the frames need with a big clock inside
+const MIN_ROWS: u16 = 20;
+
+/// Block glyphs, every row of a glyph has the same width
+fn glyph(ch: char) -> Option<[&'static str; HEIGHT]> {
+    Some(match ch {
+        '0' => ["███", "█ █", "█ █", "█ █", "███"],
+        '1' => ["  █", "  █", "  █", "  █", "  █"],
+        '2' => ["███", "  █", "███", "█  ", "███"],
+        '3' => ["███", "  █", "███", "  █", "███"],
+        '4' => ["█ █", "█ █", "███", "  █", "  █"],
+        '5' => ["███", "█  ", "███", "  █", "███"],
+        '6' => ["███", "█  ", "███", "█ █", "███"],
+        '7' => ["███", "  █", "  █", "  █", "  █"],
+        '8' => ["███", "█ █", "███", "█ █", "███"],
+        '9' => ["███", "█ █", "███", "  █", "███"],
+        ':' => [" ", "█", " ", "█", " "],
+        '.' => [" ", " ", " ", " ", "█"],
+        '+' => ["   ", " █ ", "███", " █ ", "   "],
+        '-' => ["   ", "   ", "███", "   ", "   "],
+        ' ' => [" ", " ", " ", " ", " "],
+        'h' => ["█  ", "█  ", "███", "█ █", "█ █"],
+        'm' => ["     ", "     ", "█████", "█ █ █", "█ █ █"],
+        's' => ["   ", "   ", "▄▄▄", "█▄▄", "▄▄█"],
+        _ => return None,
+    })
+}
+
+/// `text` drawn with block glyphs, `None` if a character has no glyph
+/// or the result is wider than `width`
+fn big_lines(text: &str, width: usize) -> Option<Vec<String>> {
+    let glyphs = text.chars().map(glyph).collect::<Option<Vec<_>>>()?;
+    let lines: Vec<String> = (0..HEIGHT)
+        .map(|row| {
+            glyphs
+                .iter()
+                .map(|glyph| glyph[row])
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect();
+    let fits = lines.iter().all(|line| text_width(line) <= width);
+    fits.then_some(lines)
+}
+
+/// Lines to draw `text` with: big glyphs when enabled and the terminal
+/// has room for them, the plain text otherwise
+pub fn clock_lines(text: &str, width: usize) -> Vec<String> {
+    let room =
+        terminal::size().is_ok_and(|(cols, rows)| cols as usize >= width + 2 && rows >= MIN_ROWS);
+    if settings().big_clock && room {
+        if let Some(lines) = big_lines(text, width) {
+            if settings().borders != Borders::Ascii {
+                return lines;
+            }
+            return lines
+                .iter()
+                .map(|line| line.replace(['█', '▄'], "#"))
+                .collect();
+        }
+    }
+    vec![text.to_string()]
+}
@@ -1,6 +1,11 @@
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::time::Duration;
 
-use crate::format::parse_duration;
+use crate::export::ExportFormat;
+use crate::format::{parse_duration, parse_time_bound, TimeBound, TimeStyle};
+use crate::history::RecordMode;
+use crate::stats::Period;
 use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
@@ -8,9 +13,41 @@ use clap::{Parser, Subcommand};
 pub struct Cli {
     #[command(subcommand, name = "mode")]
     pub mode: Option<CounterMode>,
+    /// Don't record finished sessions to the history file
+    #[arg(long, global = true)]
+    pub no_history: bool,
+    /// Label recorded sessions with the task you are working on
+    #[arg(long, global = true, value_name = "label")]
+    pub task: Option<String>,
+    /// Tag recorded sessions, can be given multiple times
+    #[arg(long = "tag", global = true, value_name = "tag")]
+    pub tags: Vec<String>,
+    /// Draw the time with big block digits when the terminal has room
+    #[arg(long, global = true)]
+    pub big: bool,
+    /// How to show times: default, clock, compact, human or a template like %M:%S
+    #[arg(long, global = true, value_name = "style", value_parser = TimeStyle::from_str)]
+    pub time_style: Option<TimeStyle>,
+    /// Color theme: default, mono, colorblind, nord, gruvbox or one from the config
+    #[arg(long, global = true, value_name = "name")]
+    pub theme: Option<String>,
+    /// Use the colorblind theme and mark paused clocks with an icon and label
+    #[arg(long, global = true)]
+    pub colorblind: bool,
+    /// Draw frames with plain ASCII instead of box drawing characters
+    #[arg(long, global = true)]
+    pub ascii: bool,
+    /// Language of the messages (en, de, fr), detected from LANG when not given
+    #[arg(long, global = true, value_name = "lang")]
+    pub lang: Option<String>,
+    /// Read the configuration from this file instead of the default one
+    #[arg(long, global = true, value_name = "file")]
+    pub config: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
+// rustfmt would re-indent the commented-out stopwatch arguments
+#[rustfmt::skip]
 pub enum CounterMode {
     /// alias: s, stopwatch, counts up until you tell it to stop
     #[command(name = "stopwatch", alias = "s")]
@@ -26,9 +63,10 @@ pub enum CounterMode {
     /// alias: t, timer, counts down until you tell it to stop, or it ends
     #[command(name = "timer", alias = "t")]
     Timer {
-        /// target time: example values 30m 20m 40m 2h25m30s
-        #[arg(value_parser = parse_duration, value_name = "time")]
-        target: Duration,
+        /// target time: example values 30m 90 1h30 1.5h 25:00 2h25m30s,
+        /// or a time of day: until 14:30, @17:00, tomorrow 09:00
+        #[arg(value_name = "time", required = true, num_args = 1..)]
+        target: Vec<String>,
     },
     /// alias: p, pomodoro, for all you productivity needs (default)
     #[command(name = "pomodoro", alias = "p")]
@@ -38,6 +76,97 @@ pub enum CounterMode {
         ///Display a message after quitting the pomodoro timer
         #[arg(short, name = "exitmessage")]
         exitmessage: bool,
+        /// work time, overrides the one of the chosen preset
+        #[arg(long, global = true, value_parser = parse_duration, value_name = "time")]
+        work: Option<Duration>,
+        /// short break time, overrides the one of the chosen preset
+        #[arg(
+            id = "break",
+            long,
+            global = true,
+            value_parser = parse_duration,
+            value_name = "time"
+        )]
+        break_time: Option<Duration>,
+        /// long break time, overrides the one of the chosen preset
+        #[arg(
+            id = "long-break",
+            long,
+            global = true,
+            value_parser = parse_duration,
+            value_name = "time"
+        )]
+        long_break: Option<Duration>,
+        /// number of work rounds before a long break (default 4)
+        #[arg(
+            long,
+            global = true,
+            value_name = "n",
+            value_parser = clap::value_parser!(u32).range(1..)
+        )]
+        rounds: Option<u32>,
+        /// stop by itself after this many work rounds and show a summary
+        #[arg(
+            long,
+            global = true,
+            value_name = "n",
+            value_parser = clap::value_parser!(u32).range(1..)
+        )]
+        stop_after: Option<u32>,
+    },
+    /// show which configuration files are in use or what they resolve to
+    #[command(name = "config")]
+    Config {
+        /// print the effective configuration, defaults and flags included
+        #[arg(long)]
+        print: bool,
+    },
+    /// summary of your recorded sessions
+    #[command(name = "stats")]
+    Stats {
+        /// only include sessions since: a date (2023-07-14) or a time ago (48h)
+        #[arg(long, value_parser = parse_time_bound, value_name = "when")]
+        since: Option<TimeBound>,
+        /// only include sessions until: a date (2023-07-14) or a time ago (48h)
+        #[arg(long, value_parser = parse_time_bound, value_name = "when")]
+        until: Option<TimeBound>,
+        /// group focus time by day, week or month
+        #[arg(long, value_enum, default_value_t = Period::Day)]
+        by: Period,
+        /// show completed pomodoros of the past year as a calendar heatmap
+        #[arg(long, conflicts_with_all = ["since", "until", "by"])]
+        heatmap: bool,
+    },
+    /// export recorded sessions for spreadsheets and dashboards
+    #[command(name = "export")]
+    Export {
+        /// output format
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// only export these modes, can be given multiple times
+        #[arg(long, value_enum)]
+        mode: Vec<RecordMode>,
+        /// only include sessions since: a date (2023-07-14) or a time ago (48h)
+        #[arg(long, value_parser = parse_time_bound, value_name = "when")]
+        since: Option<TimeBound>,
+        /// only include sessions until: a date (2023-07-14) or a time ago (48h)
+        #[arg(long, value_parser = parse_time_bound, value_name = "when")]
+        until: Option<TimeBound>,
+        /// write to a file instead of stdout
+        #[arg(short, long, value_name = "file")]
+        output: Option<PathBuf>,
+        /// ics: also export breaks as events
+        #[arg(long)]
+        include_breaks: bool,
+        /// ics: merge phases separated by at most this gap into one event (default 15m)
+        #[arg(
+            long,
+            value_parser = parse_duration,
+            value_name = "gap",
+            num_args = 0..=1,
+            default_missing_value = "15m"
+        )]
+        merge: Option<Duration>,
     },
 }
 
@@ -62,4 +191,25 @@ pub enum PomoMode {
         #[arg(value_parser = parse_duration, value_name = "long-break-time")]
         long_break: Duration,
     },
+    /// alias: u, a preset by name, built-in or from the config
+    #[command(name = "use", alias = "u")]
+    Use {
+        /// preset name, see `porsmo pomodoro list`
+        #[arg(value_name = "preset")]
+        name: String,
+    },
+    /// alias: ls, show all presets with their durations
+    #[command(name = "list", alias = "ls")]
+    List,
+    /// alias: ph, your own sequence of phases, repeated until you quit
+    #[command(name = "phases", alias = "ph")]
+    Phases {
+        /// phases in order, as name:duration with optional ,key=value pairs
+        /// (kind, title, color, prompt, alert, message). Phases other than
+        /// work, break and long-break are of kind other unless given one.
+        /// example: plan:5m work:25m review:5m "break:5m,color=blue".
+        /// Uses pomodoro.sequence from the config when left out
+        #[arg(value_name = "phase")]
+        phases: Vec<String>,
+    },
 }
@@ -0,0 +1,987 @@
+use crate::alert::{AlertKind, MAX_VOLUME};
+use crate::audio::AudioBackend;
+use crate::format::TimeStyle;
+use crate::hooks::Hook;
+use crate::input::{Command, KeyBinding, KeyMap, DEFAULT_KEYS, TIMEOUT};
+use crate::layout::{Panel, PauseCue, DEFAULT_PANELS};
+use crate::locale::Catalog;
+use crate::pomodoro::{Mode, Phase, PomodoroConfig};
+use crate::prelude::*;
+use crate::preset::{builtin_presets, Preset};
+use crate::terminal::parse_color;
+use crate::theme::{color_enabled, truecolor, Borders, Theme, ThemeSection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// What runs when porsmo is started without a subcommand
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DefaultMode {
+    #[default]
+    Short,
+    Long,
+    Phases,
+    Timer,
+    Stopwatch,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PomodoroSection {
+    /// Durations of the short (default) pomodoro
+    #[serde(with = "opt_duration", skip_serializing_if = "Option::is_none")]
+    pub work: Option<Duration>,
+    #[serde(
+        rename = "break",
+        with = "opt_duration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub break_time: Option<Duration>,
+    #[serde(with = "opt_duration", skip_serializing_if = "Option::is_none")]
+    pub long_break: Option<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rounds: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_after: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_message: Option<bool>,
+    /// Phase names walked through by `porsmo pomodoro phases`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sequence: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct PhaseSection {
+    #[serde(with = "opt_duration", skip_serializing_if = "Option::is_none")]
+    pub duration: Option<Duration>,
+    /// work, break, long-break or other, the default for custom phases
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// A user preset, missing durations come from the short pomodoro
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PresetSection {
+    #[serde(with = "opt_duration", skip_serializing_if = "Option::is_none")]
+    pub work: Option<Duration>,
+    #[serde(
+        rename = "break",
+        with = "opt_duration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub break_time: Option<Duration>,
+    #[serde(with = "opt_duration", skip_serializing_if = "Option::is_none")]
+    pub long_break: Option<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rounds: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimerSection {
+    /// Target used when the default mode is `timer`
+    #[serde(with = "opt_duration", skip_serializing_if = "Option::is_none")]
+    pub target: Option<Duration>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct AlertSection {
+    /// Show desktop notifications
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify: Option<bool>,
+    /// Play the bell sound
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bell: Option<bool>,
+    /// Warn this long before a timer or pomodoro phase ends
+    #[serde(with = "opt_duration", skip_serializing_if = "Option::is_none")]
+    pub warn_before: Option<Duration>,
+    /// Volume of every sound in percent, up to 200
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<u16>,
+    /// Sounds start silent and reach their volume after this long
+    #[serde(with = "opt_duration", skip_serializing_if = "Option::is_none")]
+    pub fade_in: Option<Duration>,
+    /// Where sounds are played, `null` plays nothing for machines without
+    /// a sound card
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<AudioBackend>,
+    pub sounds: SoundsSection,
+    pub volumes: VolumesSection,
+    pub alarm: AlarmSection,
+}
+
+/// How an alarm keeps ringing once a timer or phase ended
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct AlarmSection {
+    /// Ring again this often until acknowledged, rings once when unset
+    #[serde(with = "opt_duration", skip_serializing_if = "Option::is_none")]
+    pub every: Option<Duration>,
+    /// Rings at most this many more times, 10 when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Percent the volume goes up with every repetition
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub louder: Option<u16>,
+    /// Repetitions before the `urgent` sound takes over, 1 when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub urgent_after: Option<u32>,
+}
+
+/// Volume in percent of each alert's sound, relative to `volume`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct VolumesSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub work_end: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub break_end: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub long_break_start: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timer_end: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<u16>,
+}
+
+impl VolumesSection {
+    pub fn get(&self, kind: AlertKind) -> u16 {
+        let volume = match kind {
+            AlertKind::WorkEnd => self.work_end,
+            AlertKind::BreakEnd => self.break_end,
+            AlertKind::LongBreakStart => self.long_break_start,
+            AlertKind::TimerEnd => self.timer_end,
+            AlertKind::Warning => self.warning,
+        };
+        volume.unwrap_or(100)
+    }
+
+    fn all(&self) -> [Option<u16>; 5] {
+        [
+            self.work_end,
+            self.break_end,
+            self.long_break_start,
+            self.timer_end,
+            self.warning,
+        ]
+    }
+}
+
+/// Audio files (wav, ogg, flac or mp3) played instead of the built-in bell
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SoundsSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub work_end: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub break_end: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub long_break_start: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timer_end: Option<PathBuf>,
+    /// Played `warn-before` the end
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<PathBuf>,
+    /// Played by an alarm that keeps ringing, see `[alert.alarm]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub urgent: Option<PathBuf>,
+}
+
+impl SoundsSection {
+    pub fn get(&self, kind: AlertKind) -> Option<&PathBuf> {
+        match kind {
+            AlertKind::WorkEnd => self.work_end.as_ref(),
+            AlertKind::BreakEnd => self.break_end.as_ref(),
+            AlertKind::LongBreakStart => self.long_break_start.as_ref(),
+            AlertKind::TimerEnd => self.timer_end.as_ref(),
+            AlertKind::Warning => self.warning.as_ref(),
+        }
+    }
+
+    pub fn files_mut(&mut self) -> [&mut Option<PathBuf>; 6] {
+        [
+            &mut self.work_end,
+            &mut self.break_end,
+            &mut self.long_break_start,
+            &mut self.timer_end,
+            &mut self.warning,
+            &mut self.urgent,
+        ]
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct UiSection {
+    /// Milliseconds between redraws
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tick_ms: Option<u64>,
+    /// A built-in theme (default, mono, colorblind, nord, gruvbox) or one
+    /// from `[themes]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    /// Overrides the running color of the theme
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub running_color: Option<String>,
+    /// Overrides the paused color of the theme
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paused_color: Option<String>,
+    /// rounded, square or ascii
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub borders: Option<Borders>,
+    /// How paused clocks stand out besides their color: icon, label, blink
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_cues: Option<Vec<PauseCue>>,
+    /// default, clock, compact, human or a template like `%M:%S`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_style: Option<String>,
+    /// Draw the time with big block digits when the terminal has room
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub big_clock: Option<bool>,
+    /// Parts of the counter screens in order: title, clock, progress,
+    /// controls and footer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub panels: Option<Vec<Panel>>,
+    /// Show hundredths of a second on the stopwatch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subseconds: Option<bool>,
+}
+
+/// Keys for each command, replacing the built-in ones when given
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct KeysSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quit: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toggle: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resume: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reset: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enter: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub yes: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume_up: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume_down: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acknowledge: Option<Vec<String>>,
+}
+
+impl KeysSection {
+    fn bindings(&self) -> [(Command, &Option<Vec<String>>); 12] {
+        [
+            (Command::Quit, &self.quit),
+            (Command::Toggle, &self.toggle),
+            (Command::Pause, &self.pause),
+            (Command::Resume, &self.resume),
+            (Command::Reset, &self.reset),
+            (Command::Enter, &self.enter),
+            (Command::Skip, &self.skip),
+            (Command::Yes, &self.yes),
+            (Command::No, &self.no),
+            (Command::VolumeUp, &self.volume_up),
+            (Command::VolumeDown, &self.volume_down),
+            (Command::Acknowledge, &self.acknowledge),
+        ]
+    }
+
+    fn bindings_mut(&mut self) -> [(Command, &mut Option<Vec<String>>); 12] {
+        [
+            (Command::Quit, &mut self.quit),
+            (Command::Toggle, &mut self.toggle),
+            (Command::Pause, &mut self.pause),
+            (Command::Resume, &mut self.resume),
+            (Command::Reset, &mut self.reset),
+            (Command::Enter, &mut self.enter),
+            (Command::Skip, &mut self.skip),
+            (Command::Yes, &mut self.yes),
+            (Command::No, &mut self.no),
+            (Command::VolumeUp, &mut self.volume_up),
+            (Command::VolumeDown, &mut self.volume_down),
+            (Command::Acknowledge, &mut self.acknowledge),
+        ]
+    }
+}
+
+/// Identifies the project a `.porsmo.toml` belongs to
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectSection {
+    /// Defaults to the name of the directory holding `.porsmo.toml`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Added to the tags of every recorded session
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// Shell commands run as pomodoro phases start and end. They get the phase
+/// in `PORSMO_PHASE`, `PORSMO_KIND` and `PORSMO_ROUND`, and the project in
+/// `PORSMO_PROJECT`. A `.porsmo.toml` can only set them once its directory
+/// is listed in `trusted-projects` of the user config, as anyone can put
+/// one in a repository.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct HooksSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase_start: Option<String>,
+    /// Runs once a phase is finished, skipped or cut short by quitting, with
+    /// its length in seconds in `PORSMO_ELAPSED` and `PORSMO_SKIPPED` set to
+    /// `true` or `false`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase_end: Option<String>,
+}
+
+impl HooksSection {
+    pub fn command(&self, hook: Hook) -> Option<&str> {
+        match hook {
+            Hook::PhaseStart => self.phase_start.as_deref(),
+            Hook::PhaseEnd => self.phase_end.as_deref(),
+        }
+    }
+}
+
+/// Name of the per-project config, searched for from the working directory up
+pub const PROJECT_FILE: &str = ".porsmo.toml";
+
+/// Contents of `config.toml`, every value is optional
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_mode: Option<DefaultMode>,
+    /// Record finished sessions to the history file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history: Option<bool>,
+    /// Language of the messages, taken from `LANG` and friends when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    pub pomodoro: PomodoroSection,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub phases: BTreeMap<String, PhaseSection>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub presets: BTreeMap<String, PresetSection>,
+    pub timer: TimerSection,
+    pub alert: AlertSection,
+    pub ui: UiSection,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub themes: BTreeMap<String, ThemeSection>,
+    pub keys: KeysSection,
+    pub hooks: HooksSection,
+    /// Directories whose `.porsmo.toml` may set `[hooks]`, only read from
+    /// the user config
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub trusted_projects: Vec<PathBuf>,
+    pub project: ProjectSection,
+    /// The `.porsmo.toml` merged over the user config, if any
+    #[serde(skip)]
+    pub project_file: Option<PathBuf>,
+    /// The project file if its hooks were left out for not being trusted
+    #[serde(skip)]
+    pub untrusted_hooks: Option<PathBuf>,
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/porsmo/config.toml` (or the platform equivalent)
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("porsmo").join("config.toml"))
+    }
+
+    /// Nearest `.porsmo.toml` in `dir` or one of its ancestors
+    pub fn find_project_file(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|dir| dir.join(PROJECT_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// Reads a config file as a raw table, checking that it is a valid config
+    fn read_table(path: &Path) -> Result<toml::Table> {
+        let text =
+            fs::read_to_string(path).map_err(|e| PorsmoError::ConfigRead(path.to_path_buf(), e))?;
+        toml::from_str::<Self>(&text)
+            .and_then(|_| toml::from_str(&text))
+            .map_err(|e| PorsmoError::ConfigParse(path.to_path_buf(), e))
+    }
+
+    /// Loads `path` if given, otherwise the default file if it exists, then
+    /// merges the project file found from the working directory over it
+    pub fn load_or_default(path: Option<&Path>) -> Result<Self> {
+        let user = match path {
+            Some(path) => Some(path.to_path_buf()),
+            None => Self::default_path().filter(|path| path.exists()),
+        };
+        let project = std::env::current_dir()
+            .ok()
+            .and_then(|dir| Self::find_project_file(&dir));
+        let mut table = match &user {
+            Some(path) => Self::read_table(path)?,
+            None => toml::Table::new(),
+        };
+        let mut untrusted_hooks = None;
+        if let Some(path) = &project {
+            let mut overlay = Self::read_table(path)?;
+            overlay.remove("trusted-projects");
+            if overlay.contains_key("hooks") && !is_trusted(&table, path) {
+                overlay.remove("hooks");
+                untrusted_hooks = Some(path.clone());
+            }
+            merge_tables(&mut table, overlay);
+        }
+        let mut config: Self = toml::Value::Table(table).try_into().map_err(|e| {
+            let path = project.clone().or(user).unwrap_or_default();
+            PorsmoError::ConfigParse(path, e)
+        })?;
+        config.project_file = project;
+        config.untrusted_hooks = untrusted_hooks;
+        Ok(config)
+    }
+
+    /// Name of the project sessions are recorded under
+    pub fn project_name(&self) -> Option<String> {
+        self.project.name.clone().or_else(|| {
+            let dir = self.project_file.as_deref()?.parent()?;
+            Some(dir.file_name()?.to_string_lossy().into_owned())
+        })
+    }
+
+    /// The config with every built-in default filled in
+    pub fn effective(&self) -> Self {
+        let short = PomodoroConfig::short();
+        let mut config = self.clone();
+        config.default_mode.get_or_insert_with(DefaultMode::default);
+        config.project.name = self.project_name();
+        config.history.get_or_insert(true);
+        let pomodoro = &mut config.pomodoro;
+        pomodoro.work.get_or_insert(short.work_time);
+        pomodoro.break_time.get_or_insert(short.break_time);
+        pomodoro.long_break.get_or_insert(short.long_break);
+        pomodoro.rounds.get_or_insert(short.long_break_interval);
+        pomodoro.exit_message.get_or_insert(false);
+        config
+            .timer
+            .target
+            .get_or_insert(Duration::from_secs(25 * 60));
+        config.alert.notify.get_or_insert(true);
+        config.alert.bell.get_or_insert(true);
+        config.alert.volume.get_or_insert(100);
+        config
+            .alert
+            .output
+            .get_or_insert_with(AudioBackend::default);
+        config.ui.tick_ms.get_or_insert(TIMEOUT.as_millis() as u64);
+        config.ui.theme.get_or_insert_with(|| "default".into());
+        config.ui.borders.get_or_insert_default();
+        config.ui.pause_cues.get_or_insert_default();
+        config
+            .ui
+            .time_style
+            .get_or_insert_with(|| TimeStyle::default().to_string());
+        config.ui.big_clock.get_or_insert(false);
+        config.ui.subseconds.get_or_insert(false);
+        config
+            .ui
+            .panels
+            .get_or_insert_with(|| DEFAULT_PANELS.to_vec());
+        for (command, keys) in config.keys.bindings_mut() {
+            keys.get_or_insert_with(|| {
+                DEFAULT_KEYS
+                    .iter()
+                    .filter(|(bound, _)| *bound == command)
+                    .flat_map(|(_, keys)| keys.iter().map(|key| key.to_string()))
+                    .collect()
+            });
+        }
+        config
+    }
+
+    /// The short pomodoro with the durations from the config
+    pub fn pomodoro(&self) -> PomodoroConfig {
+        let short = PomodoroConfig::short();
+        PomodoroConfig::new(
+            self.pomodoro.work.unwrap_or(short.work_time),
+            self.pomodoro.break_time.unwrap_or(short.break_time),
+            self.pomodoro.long_break.unwrap_or(short.long_break),
+        )
+    }
+
+    /// Built-in presets followed by the ones from the config, a user preset
+    /// replaces a built-in one of the same name
+    pub fn presets(&self) -> Vec<Preset> {
+        let short = self.pomodoro();
+        let mut presets = builtin_presets();
+        if let Some(preset) = presets.iter_mut().find(|preset| preset.name == "short") {
+            preset.work_time = short.work_time;
+            preset.break_time = short.break_time;
+            preset.long_break = short.long_break;
+        }
+        for (name, section) in &self.presets {
+            let preset = Preset {
+                name: name.clone(),
+                description: section.description.clone().unwrap_or_default(),
+                work_time: section.work.unwrap_or(short.work_time),
+                break_time: section.break_time.unwrap_or(short.break_time),
+                long_break: section.long_break.unwrap_or(short.long_break),
+                rounds: section.rounds,
+            };
+            match presets.iter_mut().find(|builtin| builtin.name == *name) {
+                Some(builtin) => *builtin = preset,
+                None => presets.push(preset),
+            }
+        }
+        presets
+    }
+
+    pub fn preset(&self, name: &str) -> Result<Preset> {
+        self.presets()
+            .into_iter()
+            .find(|preset| preset.name == name)
+            .ok_or_else(|| PorsmoError::UnknownPreset(name.to_string()))
+    }
+
+    /// Resolves `pomodoro.sequence` into phases, the built-in work, break
+    /// and long-break phases can be used without defining them
+    pub fn phase_sequence(&self) -> Result<Vec<Phase>> {
+        let pomodoro = self.pomodoro();
+        let rounds = self.pomodoro.rounds.unwrap_or(pomodoro.long_break_interval);
+        self.pomodoro
+            .sequence
+            .iter()
+            .map(|name| {
+                let base = match name.as_str() {
+                    "work" => Phase::work(pomodoro.work_time),
+                    "break" => Phase::short_break(pomodoro.break_time),
+                    "long-break" => Phase::long_break(pomodoro.long_break, rounds),
+                    _ if self.phases.contains_key(name) => {
+                        Phase::named(name, Mode::Other, pomodoro.work_time)
+                    }
+                    _ => return Err(PorsmoError::UnknownPhase(name.clone())),
+                };
+                match self.phases.get(name) {
+                    Some(section) => section.apply(base),
+                    None => Ok(base),
+                }
+            })
+            .collect()
+    }
+
+    /// The theme named by `ui.theme`, custom themes shadow built-in ones
+    pub fn theme(&self) -> Result<Theme> {
+        let name = self.ui.theme.as_deref().unwrap_or("default");
+        match self.themes.get(name) {
+            Some(section) => section.theme(),
+            None => name.parse(),
+        }
+    }
+
+    pub fn settings(&self) -> Result<Settings> {
+        let volumes = self.alert.volumes.all().into_iter().flatten();
+        if let Some(volume) = volumes
+            .chain(self.alert.volume)
+            .find(|volume| *volume > MAX_VOLUME)
+        {
+            return Err(PorsmoError::VolumeOutOfRange(volume));
+        }
+        let mut keymap = KeyMap::default();
+        for (command, keys) in self.keys.bindings() {
+            if let Some(keys) = keys {
+                let keys = keys
+                    .iter()
+                    .map(|key| key.parse())
+                    .collect::<Result<Vec<KeyBinding>>>()?;
+                keymap.bind(command, &keys);
+            }
+        }
+        let mut theme = self.theme()?;
+        if let Some(color) = &self.ui.running_color {
+            theme.running = parse_color(color)?;
+        }
+        if let Some(color) = &self.ui.paused_color {
+            theme.paused = parse_color(color)?;
+        }
+        if !truecolor() {
+            theme.gradient = None;
+        }
+        Ok(Settings {
+            keymap,
+            tick: self.ui.tick_ms.map_or(TIMEOUT, Duration::from_millis),
+            notify: self.alert.notify.unwrap_or(true),
+            bell: self.alert.bell.unwrap_or(true),
+            warn_before: self.alert.warn_before,
+            volume: self.alert.volume.unwrap_or(100),
+            fade_in: self.alert.fade_in,
+            audio: self.alert.output.unwrap_or_default(),
+            sounds: self.alert.sounds.clone(),
+            volumes: self.alert.volumes.clone(),
+            alarm: self.alert.alarm.clone(),
+            hooks: self.hooks.clone(),
+            theme,
+            borders: self.ui.borders.unwrap_or_default(),
+            color: color_enabled(),
+            pause_cues: self.ui.pause_cues.clone().unwrap_or_default(),
+            catalog: match &self.lang {
+                Some(lang) => Catalog::new(lang)?,
+                None => Catalog::detect(),
+            },
+            time_style: match &self.ui.time_style {
+                Some(style) => style.parse()?,
+                None => TimeStyle::default(),
+            },
+            big_clock: self.ui.big_clock.unwrap_or(false),
+            subseconds: self.ui.subseconds.unwrap_or(false),
+            panels: self
+                .ui
+                .panels
+                .clone()
+                .unwrap_or_else(|| DEFAULT_PANELS.to_vec()),
+        })
+    }
+}
+
+impl PhaseSection {
+    fn apply(&self, mut phase: Phase) -> Result<Phase> {
+        if let Some(kind) = &self.kind {
+            phase.kind = kind.parse()?;
+        }
+        if let Some(duration) = self.duration {
+            phase.duration = duration;
+        }
+        if let Some(title) = &self.title {
+            phase.title = title.clone();
+        }
+        if let Some(color) = &self.color {
+            phase.color = Some(parse_color(color)?);
+        }
+        if let Some(prompt) = &self.prompt {
+            phase.prompt = prompt.clone();
+        }
+        if let Some(alert) = &self.alert {
+            phase.alert_title = alert.clone();
+        }
+        if let Some(message) = &self.message {
+            phase.alert_message = message.clone();
+        }
+        Ok(phase)
+    }
+}
+
+/// Runtime settings derived from the config
+#[derive(Clone, Debug)]
+pub struct Settings {
+    pub keymap: KeyMap,
+    pub tick: Duration,
+    pub notify: bool,
+    pub bell: bool,
+    pub warn_before: Option<Duration>,
+    /// Starting master volume in percent, changed at runtime with the keys
+    pub volume: u16,
+    pub fade_in: Option<Duration>,
+    pub audio: AudioBackend,
+    pub sounds: SoundsSection,
+    pub volumes: VolumesSection,
+    pub alarm: AlarmSection,
+    pub hooks: HooksSection,
+    pub theme: Theme,
+    pub borders: Borders,
+    /// False when `NO_COLOR` is set
+    pub color: bool,
+    pub pause_cues: Vec<PauseCue>,
+    pub catalog: Catalog,
+    pub time_style: TimeStyle,
+    pub big_clock: bool,
+    pub subseconds: bool,
+    pub panels: Vec<Panel>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Config::default()
+            .settings()
+            .expect("built-in settings are valid")
+    }
+}
+
+/// Merges `overlay` into `base`, nested tables are merged key by key
+/// Whether the directory of `project_file` is in the `trusted-projects` of
+/// the user config `table`
+fn is_trusted(table: &toml::Table, project_file: &Path) -> bool {
+    let Some(dir) = project_file
+        .parent()
+        .and_then(|dir| dir.canonicalize().ok())
+    else {
+        return false;
+    };
+    let trusted: Vec<PathBuf> = table
+        .get("trusted-projects")
+        .cloned()
+        .and_then(|value| value.try_into().ok())
+        .unwrap_or_default();
+    trusted
+        .iter()
+        .any(|path| path.canonicalize().is_ok_and(|path| path == dir))
+}
+
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                merge_tables(base, overlay)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Makes `settings` available through [`settings()`]. Panics if they were
+/// already installed or read, since whatever read them got the defaults
+pub fn install(settings: Settings) {
+    if SETTINGS.set(settings).is_err() {
+        panic!("settings were used before the configuration was installed");
+    }
+}
+
+pub fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::default)
+}
+
+mod opt_duration {
+    use crate::format::{compact_duration, parse_duration};
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(dur: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        match dur {
+            Some(dur) => s.serialize_str(&compact_duration(*dur)),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+        Option::<String>::deserialize(d)?
+            .map(|text| parse_duration(&text).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn parse(text: &str) -> Config {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn config_files_set_durations_and_keys() {
+        let config = parse(
+            r#"
+            default-mode = "timer"
+            [pomodoro]
+            work = "50m"
+            break = "10m"
+            [keys]
+            quit = ["x", "ctrl+q"]
+            "#,
+        );
+        assert_eq!(config.default_mode, Some(DefaultMode::Timer));
+        let pomodoro = config.pomodoro();
+        assert_eq!(pomodoro.work_time, 50 * MINUTE);
+        assert_eq!(pomodoro.break_time, 10 * MINUTE);
+        assert_eq!(pomodoro.long_break, 10 * MINUTE);
+
+        let keymap = config.settings().unwrap().keymap;
+        let press = |code, modifiers| keymap.command(KeyEvent::new(code, modifiers));
+        assert_eq!(press(KeyCode::Char('x'), KeyModifiers::NONE), Command::Quit);
+        assert_eq!(
+            press(KeyCode::Char('q'), KeyModifiers::CONTROL),
+            Command::Quit
+        );
+        assert_eq!(
+            press(KeyCode::Char('q'), KeyModifiers::NONE),
+            Command::Invalid
+        );
+    }
+
+    #[test]
+    fn bad_configs_are_rejected() {
+        for text in [
+            "colour = true",
+            "[pomodoro]\nwork = \"soon\"",
+            "[ui]\nborders = 3",
+        ] {
+            assert!(toml::from_str::<Config>(text).is_err(), "{text}");
+        }
+        let bad_key = parse("[keys]\nquit = [\"super+q\"]");
+        assert!(matches!(
+            bad_key.settings(),
+            Err(PorsmoError::InvalidKey(key)) if key == "super+q"
+        ));
+        let loud = parse("[alert]\nvolume = 300");
+        assert!(matches!(
+            loud.settings(),
+            Err(PorsmoError::VolumeOutOfRange(300))
+        ));
+    }
+
+    #[test]
+    fn the_effective_config_reads_back() {
+        let effective = Config::default().effective();
+        assert_eq!(effective.pomodoro.work, Some(25 * MINUTE));
+        assert_eq!(effective.history, Some(true));
+        assert_eq!(effective.ui.theme.as_deref(), Some("default"));
+        let text = toml::to_string(&effective).unwrap();
+        let read_back = parse(&text);
+        assert_eq!(read_back.pomodoro.long_break, Some(10 * MINUTE));
+        assert_eq!(read_back.alert.volume, Some(100));
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("porsmo-test-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn project_tables_merge_over_the_user_config() {
+        let mut user: toml::Table = toml::from_str(
+            r#"
+            lang = "de"
+            [pomodoro]
+            work = "50m"
+            break = "10m"
+            "#,
+        )
+        .unwrap();
+        let project: toml::Table = toml::from_str(
+            r#"
+            [pomodoro]
+            work = "30m"
+            [project]
+            tags = ["porsmo"]
+            "#,
+        )
+        .unwrap();
+        merge_tables(&mut user, project);
+        let config: Config = toml::Value::Table(user).try_into().unwrap();
+        assert_eq!(config.lang.as_deref(), Some("de"));
+        assert_eq!(config.pomodoro.work, Some(30 * MINUTE));
+        assert_eq!(config.pomodoro.break_time, Some(10 * MINUTE));
+        assert_eq!(config.project.tags, ["porsmo"]);
+    }
+
+    #[test]
+    fn project_files_are_found_in_parent_directories() {
+        let root = temp_dir("discovery");
+        let nested = root.join("src").join("deep");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(Config::find_project_file(&nested), None);
+        fs::write(root.join(PROJECT_FILE), "").unwrap();
+        assert_eq!(
+            Config::find_project_file(&nested),
+            Some(root.join(PROJECT_FILE))
+        );
+        let config = Config {
+            project_file: Config::find_project_file(&nested),
+            ..Config::default()
+        };
+        let name = root.file_name().unwrap().to_string_lossy();
+        assert_eq!(config.project_name().as_deref(), Some(name.as_ref()));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn only_listed_projects_are_trusted() {
+        let root = temp_dir("trust");
+        fs::create_dir(root.join("src")).unwrap();
+        let file = root.join(PROJECT_FILE);
+        let trusting = |dirs: &[&Path]| {
+            let mut table = toml::Table::new();
+            let dirs = dirs
+                .iter()
+                .map(|dir| toml::Value::from(dir.display().to_string()))
+                .collect();
+            table.insert("trusted-projects".into(), toml::Value::Array(dirs));
+            table
+        };
+        assert!(!is_trusted(&toml::Table::new(), &file));
+        assert!(!is_trusted(&trusting(&[&root.join("other")]), &file));
+        assert!(is_trusted(&trusting(&[&root]), &file));
+        // the same directory spelled differently
+        assert!(is_trusted(
+            &trusting(&[&root.join("src").join("..")]),
+            &file
+        ));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn user_presets_replace_and_extend_the_built_in_ones() {
+        let config = parse(
+            r#"
+            [pomodoro]
+            work = "30m"
+            [presets.long]
+            work = "45m"
+            [presets.study]
+            break = "15m"
+            rounds = 3
+            description = "exam season"
+            "#,
+        );
+        let presets = config.presets();
+        let names: Vec<&str> = presets.iter().map(|preset| preset.name.as_str()).collect();
+        assert_eq!(names.first(), Some(&"short"));
+        assert_eq!(names.last(), Some(&"study"));
+        assert_eq!(names.iter().filter(|name| **name == "long").count(), 1);
+
+        // `short` follows [pomodoro], unset preset values do too
+        assert_eq!(config.preset("short").unwrap().work_time, 30 * MINUTE);
+        let long = config.preset("long").unwrap();
+        assert_eq!((long.work_time, long.break_time), (45 * MINUTE, 5 * MINUTE));
+        let study = config.preset("study").unwrap();
+        assert_eq!(study.work_time, 30 * MINUTE);
+        assert_eq!(study.break_time, 15 * MINUTE);
+        assert_eq!(study.rounds, Some(3));
+        assert_eq!(study.description, "exam season");
+        assert!(matches!(
+            config.preset("nap"),
+            Err(PorsmoError::UnknownPreset(name)) if name == "nap"
+        ));
+    }
+}
@@ -1,15 +1,91 @@
+use crate::locale::{tr, tr_with};
+use std::fmt;
 use std::num::ParseIntError;
+use std::path::PathBuf;
 
 #[derive(Debug, thiserror::Error)]
 pub enum PorsmoError {
-    #[error("Error entering raw mode in terminal")]
+    #[error("{}", tr("error-raw-mode"))]
     FailedRawModeEnter(#[source] crossterm::ErrorKind),
 
-    #[error("Error initializing terminal with alternate screen and mouse capture")]
+    #[error("{}", tr("error-terminal-init"))]
     FailedInitialization(#[source] crossterm::ErrorKind),
 
-    #[error("Wrong format for time")]
-    WrongFormatError,
+    /// The phase as given and the locale key of what's wrong with it
+    #[error("{}", tr_with("error-invalid-phase", &[("phase", .0), ("reason", &tr(.1))]))]
+    InvalidPhase(String, &'static str),
+
+    #[error("{}", tr_with("error-unknown-phase-kind", &[("kind", .0)]))]
+    UnknownPhaseKind(String),
+
+    #[error("{}", tr_with("error-unknown-phase", &[("phase", .0)]))]
+    UnknownPhase(String),
+
+    #[error("{}", tr_with("error-unknown-preset", &[("preset", .0)]))]
+    UnknownPreset(String),
+
+    /// Locale key of the phase's name
+    #[error("{}", tr_with("error-zero-duration", &[("phase", &tr(.0))]))]
+    ZeroDuration(&'static str),
+
+    #[error("{}", tr_with("error-long-break-too-short", &[("long", .0), ("short", .1)]))]
+    LongBreakTooShort(String, String),
+
+    #[error("{}", tr("error-durations-with-phases"))]
+    DurationsWithPhases,
+
+    #[error("{}", tr("error-no-phases"))]
+    NoPhases,
+
+    #[error("{}", tr_with("error-invalid-key", &[("key", .0)]))]
+    InvalidKey(String),
+
+    #[error("{}", tr_with("error-config-read", &[("file", &.0.display())]))]
+    ConfigRead(PathBuf, #[source] std::io::Error),
+
+    #[error("{}", tr_with("error-config-parse", &[("file", &.0.display())]))]
+    ConfigParse(PathBuf, #[source] toml::de::Error),
+
+    #[error("{}", tr("error-config-print"))]
+    ConfigPrint(#[source] toml::ser::Error),
+
+    #[error("{}", tr_with("error-unknown-time-style", &[("style", .0)]))]
+    UnknownTimeStyle(String),
+
+    #[error("{}", tr_with("error-unknown-theme", &[("theme", .0)]))]
+    UnknownTheme(String),
+
+    #[error("{}", tr_with("error-unknown-language", &[("lang", .0), ("known", .1)]))]
+    UnknownLanguage(String, String),
+
+    #[error("{}", tr_with("error-volume-out-of-range", &[("volume", .0)]))]
+    VolumeOutOfRange(u16),
+
+    #[error("{}", tr_with("error-unknown-color", &[("color", .0)]))]
+    UnknownColor(String),
+
+    #[error("{reason}\n  {input}\n  {caret}^", caret = " ".repeat(*.position))]
+    InvalidDuration {
+        input: String,
+        /// Character offset of the problem in `input`
+        position: usize,
+        reason: DurationProblem,
+    },
+
+    #[error("{}", tr_with("error-invalid-clock-time", &[("time", .0)]))]
+    InvalidClockTime(String),
+
+    #[error("{}", tr_with("error-target-in-past", &[("time", .0)]))]
+    TargetInPast(String),
+
+    #[error("{}", tr("error-history-io"))]
+    HistoryIo(#[source] std::io::Error),
+
+    #[error("{}", tr("error-history-format"))]
+    HistoryFormat(#[source] serde_json::Error),
+
+    #[error("{}", tr_with("error-create-file", &[("file", &.0.display())]))]
+    FailedToCreateFile(PathBuf, #[source] std::io::Error),
 
     #[error(transparent)]
     ParseIntError(#[from] ParseIntError),
@@ -17,3 +93,42 @@ pub enum PorsmoError {
     #[error(transparent)]
     CrosstermError(#[from] crossterm::ErrorKind),
 }
+
+/// What [`PorsmoError::InvalidDuration`] found wrong, units are the locale
+/// keys of their names
+#[derive(Debug)]
+pub enum DurationProblem {
+    Empty,
+    ExpectedNumber(char),
+    InvalidNumber(String),
+    MissingUnit,
+    UnknownUnit(String),
+    UnitTwice(&'static str),
+    UnitOrder(&'static str, &'static str),
+    TooLong,
+    ExpectedClock,
+    SixtyOrMore(String),
+}
+
+impl fmt::Display for DurationProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            Self::Empty => tr("duration-empty").to_string(),
+            Self::ExpectedNumber(found) => tr_with("duration-expected-number", &[("found", found)]),
+            Self::InvalidNumber(number) => {
+                tr_with("duration-invalid-number", &[("number", number)])
+            }
+            Self::MissingUnit => tr("duration-missing-unit").to_string(),
+            Self::UnknownUnit(unit) => tr_with("duration-unknown-unit", &[("unit", unit)]),
+            Self::UnitTwice(unit) => tr_with("duration-unit-twice", &[("unit", &tr(unit))]),
+            Self::UnitOrder(unit, previous) => tr_with(
+                "duration-unit-order",
+                &[("unit", &tr(unit)), ("previous", &tr(previous))],
+            ),
+            Self::TooLong => tr("duration-too-long").to_string(),
+            Self::ExpectedClock => tr("duration-expected-clock").to_string(),
+            Self::SixtyOrMore(part) => tr_with("duration-sixty-or-more", &[("part", part)]),
+        };
+        f.write_str(&message)
+    }
+}
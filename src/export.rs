@@ -0,0 +1,193 @@
+use crate::format::TimeBound;
+use crate::history::{History, Record, RecordMode};
+use crate::ical::write_ical;
+use crate::prelude::*;
+use clap::ValueEnum;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+    Ndjson,
+    /// iCalendar, work phases become calendar events
+    Ics,
+}
+
+/// Options that only apply to the ics format
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IcsOptions {
+    pub include_breaks: bool,
+    /// Merge phases starting at most this long after the previous one ended
+    pub merge: Option<Duration>,
+}
+
+const CSV_HEADER: &str =
+    "mode,phase,round,target,elapsed,overtime,skipped,start,end,tags,task,project";
+
+/// Quotes a CSV field if it contains a separator, quote or newline
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn csv_row(record: &Record) -> String {
+    let secs = |dur: std::time::Duration| format!("{:.3}", dur.as_secs_f64());
+    [
+        record.mode.name().to_string(),
+        record.phase.clone().unwrap_or_default(),
+        record
+            .round
+            .map(|round| round.to_string())
+            .unwrap_or_default(),
+        record.target.map(secs).unwrap_or_default(),
+        secs(record.elapsed),
+        secs(record.overtime),
+        record.skipped.to_string(),
+        record.start.to_rfc3339(),
+        record.end.to_rfc3339(),
+        record.tags.join(";"),
+        record.task.clone().unwrap_or_default(),
+        record.project.clone().unwrap_or_default(),
+    ]
+    .iter()
+    .map(|field| csv_field(field))
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+pub fn export_records(
+    out: &mut impl Write,
+    records: &[&Record],
+    format: ExportFormat,
+    ics: IcsOptions,
+) -> Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            writeln!(out, "{CSV_HEADER}")?;
+            for record in records {
+                writeln!(out, "{}", csv_row(record))?;
+            }
+        }
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, records).map_err(PorsmoError::HistoryFormat)?;
+            writeln!(out)?;
+        }
+        ExportFormat::Ndjson => {
+            for record in records {
+                serde_json::to_writer(&mut *out, record).map_err(PorsmoError::HistoryFormat)?;
+                writeln!(out)?;
+            }
+        }
+        ExportFormat::Ics => write_ical(out, records, ics.include_breaks, ics.merge)?,
+    }
+    out.flush()?;
+    Ok(())
+}
+
+pub fn run_export(
+    history: &History,
+    format: ExportFormat,
+    modes: &[RecordMode],
+    since: Option<TimeBound>,
+    until: Option<TimeBound>,
+    output: Option<&Path>,
+    ics: IcsOptions,
+) -> Result<()> {
+    let since = since.map(|bound| bound.start());
+    let until = until.map(|bound| bound.end());
+    let records = history.load()?;
+    let records: Vec<&Record> = records
+        .iter()
+        .filter(|record| record.started_within(since, until))
+        .filter(|record| modes.is_empty() || modes.contains(&record.mode))
+        .collect();
+    match output {
+        Some(path) => {
+            let file = File::create(path)
+                .map_err(|e| PorsmoError::FailedToCreateFile(path.to_path_buf(), e))?;
+            export_records(&mut BufWriter::new(file), &records, format, ics)
+        }
+        None => export_records(&mut std::io::stdout(), &records, format, ics),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+
+    fn record() -> Record {
+        let start = Local.with_ymd_and_hms(2026, 10, 16, 9, 0, 0).unwrap();
+        Record {
+            mode: RecordMode::Work,
+            phase: Some("deep work".into()),
+            round: Some(2),
+            start,
+            end: start + chrono::Duration::minutes(26),
+            target: Some(Duration::from_secs(1500)),
+            elapsed: Duration::from_millis(1_560_500),
+            overtime: Duration::from_millis(60_500),
+            skipped: false,
+            tags: vec!["a".into(), "b".into()],
+            task: Some("say \"hi\", then go".into()),
+            project: Some("porsmo".into()),
+        }
+    }
+
+    fn export(format: ExportFormat) -> String {
+        let mut out = Vec::new();
+        export_records(&mut out, &[&record()], format, IcsOptions::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a;b"), "a;b");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+    }
+
+    #[test]
+    fn csv_rows_keep_the_header_columns() {
+        let text = export(ExportFormat::Csv);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(
+            lines[1].starts_with("work,deep work,2,1500.000,1560.500,60.500,false,"),
+            "{}",
+            lines[1]
+        );
+        assert!(
+            lines[1].ends_with(",a;b,\"say \"\"hi\"\", then go\",porsmo"),
+            "{}",
+            lines[1]
+        );
+        assert_eq!(
+            lines[1].matches(',').count(),
+            CSV_HEADER.matches(',').count() + 1
+        );
+    }
+
+    #[test]
+    fn json_exports_read_back_as_records() {
+        let records: Vec<Record> = serde_json::from_str(&export(ExportFormat::Json)).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].task, record().task);
+        assert_eq!(records[0].elapsed, record().elapsed);
+        let ndjson = export(ExportFormat::Ndjson);
+        assert_eq!(ndjson.lines().count(), 1);
+        let line: Record = serde_json::from_str(ndjson.trim_end()).unwrap();
+        assert_eq!(line.phase.as_deref(), Some("deep work"));
+    }
+}
@@ -1,6 +1,13 @@
-use std::time::Duration;
 use std::borrow::Borrow;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
 
+use chrono::{DateTime, Local, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+
+use crate::config::settings;
+use crate::error::DurationProblem;
+use crate::locale::{count, tr_with};
 use crate::prelude::*;
 
 pub fn format_duration(dur: impl Borrow<Duration>) -> String {
@@ -12,31 +19,550 @@ pub fn format_duration(dur: impl Borrow<Duration>) -> String {
     format!("{hours}h {mins}m {secs}s")
 }
 
+/// How durations are shown in the TUI, notifications and exit message
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum TimeStyle {
+    /// `0h 4m 5s`
+    #[default]
+    Default,
+    /// `00:04:05`
+    Clock,
+    /// `4:05`, `1:04:05`
+    Compact,
+    /// `4 minutes`
+    Human,
+    /// `%H:%M:%S` style template, see [`TimeStyle::format`]
+    Template(String),
+}
+
+impl FromStr for TimeStyle {
+    type Err = PorsmoError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "default" => Ok(Self::Default),
+            "clock" => Ok(Self::Clock),
+            "compact" => Ok(Self::Compact),
+            "human" => Ok(Self::Human),
+            _ if s.contains('%') => Ok(Self::Template(s.to_string())),
+            _ => Err(PorsmoError::UnknownTimeStyle(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for TimeStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => f.write_str("default"),
+            Self::Clock => f.write_str("clock"),
+            Self::Compact => f.write_str("compact"),
+            Self::Human => f.write_str("human"),
+            Self::Template(template) => f.write_str(template),
+        }
+    }
+}
+
+impl TimeStyle {
+    /// Formats `dur` in this style. Templates replace `%H`, `%M` and `%S`
+    /// with zero padded hours, minutes and seconds, `%h`, `%m` and `%s`
+    /// with unpadded ones, `%f` with hundredths of a second and `%%` with `%`
+    pub fn format(&self, dur: impl Borrow<Duration>) -> String {
+        let dur = dur.borrow();
+        let total_secs = dur.as_secs();
+        let (hours, mins, secs) = (total_secs / 3600, total_secs / 60 % 60, total_secs % 60);
+        match self {
+            Self::Default => format_duration(dur),
+            Self::Clock => format!("{hours:02}:{mins:02}:{secs:02}"),
+            Self::Compact if hours > 0 => format!("{hours}:{mins:02}:{secs:02}"),
+            Self::Compact => format!("{mins}:{secs:02}"),
+            Self::Human => match (hours, mins) {
+                (0, 0) => count(secs, "seconds"),
+                (0, mins) => count(mins, "minutes"),
+                (hours, 0) => count(hours, "hours"),
+                (hours, mins) => format!("{} {}", count(hours, "hours"), count(mins, "minutes")),
+            },
+            Self::Template(template) => {
+                let mut text = String::new();
+                let mut chars = template.chars();
+                while let Some(ch) = chars.next() {
+                    if ch != '%' {
+                        text.push(ch);
+                        continue;
+                    }
+                    match chars.next() {
+                        Some('H') => text.push_str(&format!("{hours:02}")),
+                        Some('M') => text.push_str(&format!("{mins:02}")),
+                        Some('S') => text.push_str(&format!("{secs:02}")),
+                        Some('h') => text.push_str(&hours.to_string()),
+                        Some('m') => text.push_str(&mins.to_string()),
+                        Some('s') => text.push_str(&secs.to_string()),
+                        Some('f') => text.push_str(&format!("{:02}", dur.subsec_millis() / 10)),
+                        Some('%') => text.push('%'),
+                        Some(other) => {
+                            text.push('%');
+                            text.push(other);
+                        }
+                        None => text.push('%'),
+                    }
+                }
+                text
+            }
+        }
+    }
+
+    /// Time left on a countdown, humanized as `4 minutes left`
+    pub fn countdown(&self, dur: impl Borrow<Duration>) -> String {
+        match self {
+            Self::Human => tr_with("time-left", &[("time", &self.format(dur))]),
+            _ => self.format(dur),
+        }
+    }
+
+    /// Like [`TimeStyle::format`] with hundredths of a second, templates
+    /// only show them through `%f`
+    pub fn precise(&self, dur: impl Borrow<Duration>) -> String {
+        let dur = dur.borrow();
+        let hundredths = dur.subsec_millis() / 10;
+        match self {
+            Self::Default => {
+                let text = format_duration(dur);
+                format!("{}.{hundredths:02}s", text.trim_end_matches('s'))
+            }
+            Self::Clock | Self::Compact => format!("{}.{hundredths:02}", self.format(dur)),
+            Self::Human | Self::Template(_) => self.format(dur),
+        }
+    }
+}
+
+/// Formats `dur` in the configured [`TimeStyle`]
+pub fn display_duration(dur: impl Borrow<Duration>) -> String {
+    settings().time_style.format(dur)
+}
+
+/// Shortest form `parse_duration` reads back, e.g. `25m` or `1h30m`
+pub fn compact_duration(dur: impl Borrow<Duration>) -> String {
+    let dur = dur.borrow();
+    let total_secs = dur.as_secs();
+    let (hours, mins, secs) = (total_secs / 3600, total_secs / 60 % 60, total_secs % 60);
+    let nanos = dur.subsec_nanos();
+    let mut text = String::new();
+    if hours > 0 {
+        text.push_str(&format!("{hours}h"));
+    }
+    if mins > 0 {
+        text.push_str(&format!("{mins}m"));
+    }
+    if nanos > 0 {
+        let fraction = format!("{nanos:09}");
+        text.push_str(&format!("{secs}.{}s", fraction.trim_end_matches('0')));
+    } else if secs > 0 || text.is_empty() {
+        text.push_str(&format!("{secs}s"));
+    }
+    text
+}
+
+/// Units from smallest to largest with their length in seconds
+const UNITS: [(&str, f64); 4] = [
+    ("unit-seconds", 1.0),
+    ("unit-minutes", 60.0),
+    ("unit-hours", 3600.0),
+    ("unit-days", 86400.0),
+];
+
+/// Index into [`UNITS`] for a unit as written, case insensitive
+fn unit_rank(unit: &str) -> Option<usize> {
+    match unit.to_lowercase().as_str() {
+        "s" | "sec" | "secs" | "second" | "seconds" => Some(0),
+        "m" | "min" | "mins" | "minute" | "minutes" => Some(1),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Some(2),
+        "d" | "day" | "days" => Some(3),
+        _ => None,
+    }
+}
+
+/// Parses durations such as `25m`, `1h30m`, `1h 30m`, `1h30`, `1.5h`,
+/// `90` (minutes), `2d`, `25:00` (minutes:seconds) and `1:30:00`.
+/// Errors point at the character that couldn't be read.
 pub fn parse_duration(text: &str) -> Result<Duration> {
-    let (hours, text) = match text.split_once('h') {
-        Some((hours, rest)) => {
-            let hours = hours.parse::<u64>()?;
-            (Duration::from_secs(hours * 3600), rest)
-        },
-        None => (Duration::ZERO, text),
+    let error = |position: usize, reason: DurationProblem| PorsmoError::InvalidDuration {
+        input: text.to_string(),
+        position,
+        reason,
+    };
+    let chars: Vec<char> = text.chars().collect();
+    if text.trim().is_empty() {
+        return Err(error(0, DurationProblem::Empty));
+    }
+    if text.contains(':') {
+        return parse_clock(text);
+    }
+
+    let skip_whitespace = |mut pos: usize| {
+        while pos < chars.len() && chars[pos].is_whitespace() {
+            pos += 1;
+        }
+        pos
+    };
+    let mut secs = 0.0;
+    let mut last_rank: Option<usize> = None;
+    let mut pos = skip_whitespace(0);
+    while pos < chars.len() {
+        let start = pos;
+        while pos < chars.len() && (chars[pos].is_ascii_digit() || chars[pos] == '.') {
+            pos += 1;
+        }
+        if start == pos {
+            return Err(error(pos, DurationProblem::ExpectedNumber(chars[pos])));
+        }
+        let number: String = chars[start..pos].iter().collect();
+        let value: f64 = number
+            .parse()
+            .map_err(|_| error(start, DurationProblem::InvalidNumber(number.clone())))?;
+
+        pos = skip_whitespace(pos);
+        let unit_start = pos;
+        while pos < chars.len() && chars[pos].is_alphabetic() {
+            pos += 1;
+        }
+        let unit: String = chars[unit_start..pos].iter().collect();
+        let rank = if unit.is_empty() {
+            // a bare number is in the unit after the previous one, minutes on its own
+            match last_rank {
+                None => 1,
+                Some(0) => return Err(error(start, DurationProblem::MissingUnit)),
+                Some(rank) => rank - 1,
+            }
+        } else {
+            unit_rank(&unit)
+                .ok_or_else(|| error(unit_start, DurationProblem::UnknownUnit(unit.clone())))?
+        };
+        match last_rank {
+            Some(last) if rank == last => {
+                return Err(error(start, DurationProblem::UnitTwice(UNITS[rank].0)));
+            }
+            Some(last) if rank > last => {
+                return Err(error(
+                    start,
+                    DurationProblem::UnitOrder(UNITS[rank].0, UNITS[last].0),
+                ));
+            }
+            _ => (),
+        }
+        secs += value * UNITS[rank].1;
+        last_rank = Some(rank);
+        pos = skip_whitespace(pos);
+    }
+    Duration::try_from_secs_f64(secs).map_err(|_| error(0, DurationProblem::TooLong))
+}
+
+/// `minutes:seconds` or `hours:minutes:seconds`
+fn parse_clock(text: &str) -> Result<Duration> {
+    let error = |position: usize, reason: DurationProblem| PorsmoError::InvalidDuration {
+        input: text.to_string(),
+        position,
+        reason,
     };
+    let leading = text.chars().take_while(|ch| ch.is_whitespace()).count();
+    let parts: Vec<&str> = text.trim().split(':').collect();
+    if parts.len() > 3 {
+        let third = text.match_indices(':').nth(2).map_or(0, |(at, _)| at);
+        return Err(error(
+            text[..third].chars().count(),
+            DurationProblem::ExpectedClock,
+        ));
+    }
+    let mut secs = 0.0;
+    let mut position = leading;
+    for (index, part) in parts.iter().enumerate() {
+        let last = index == parts.len() - 1;
+        let invalid = part
+            .char_indices()
+            .find(|(_, ch)| !(ch.is_ascii_digit() || (last && *ch == '.')))
+            .map(|(at, _)| part[..at].chars().count());
+        let value: f64 = match (invalid, part.parse()) {
+            (None, Ok(value)) => value,
+            (Some(offset), _) => {
+                let found = part[..].chars().nth(offset).unwrap_or_default();
+                return Err(error(
+                    position + offset,
+                    DurationProblem::ExpectedNumber(found),
+                ));
+            }
+            (None, Err(_)) => {
+                return Err(error(
+                    position,
+                    DurationProblem::InvalidNumber(part.to_string()),
+                ))
+            }
+        };
+        if index > 0 && value >= 60.0 {
+            return Err(error(
+                position,
+                DurationProblem::SixtyOrMore(part.to_string()),
+            ));
+        }
+        secs = secs * 60.0 + value;
+        position += part.chars().count() + 1;
+    }
+    Duration::try_from_secs_f64(secs).map_err(|_| error(0, DurationProblem::TooLong))
+}
+
+/// A point in time given on the command line, either a calendar date
+/// (`2023-07-14`) or a duration back from now (`48h`).
+#[derive(Clone, Copy, Debug)]
+pub enum TimeBound {
+    Date(NaiveDate),
+    Ago(Duration),
+}
+
+impl TimeBound {
+    /// Beginning of the bound: midnight for dates
+    pub fn start(&self) -> DateTime<Local> {
+        match self {
+            Self::Date(date) => local_midnight(*date),
+            Self::Ago(dur) => Local::now() - chrono::Duration::from_std(*dur).unwrap_or_default(),
+        }
+    }
+
+    /// End of the bound: dates include the whole day
+    pub fn end(&self) -> DateTime<Local> {
+        match self {
+            Self::Date(date) => local_midnight(date.succ_opt().unwrap_or(*date)),
+            Self::Ago(_) => self.start(),
+        }
+    }
+}
+
+pub fn local_midnight(date: NaiveDate) -> DateTime<Local> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&midnight))
+}
 
-    let (mins, text) = match text.split_once('m') {
-        Some((mins, text)) => {
-            let mins = mins.parse::<u64>()?;
-            (Duration::from_secs(mins * 60), text)
-        },
-        None => (Duration::ZERO, text),
+pub fn parse_time_bound(text: &str) -> Result<TimeBound> {
+    match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        Ok(date) => Ok(TimeBound::Date(date)),
+        Err(_) => parse_duration(text).map(TimeBound::Ago),
+    }
+}
+
+/// What a timer counts down to: a duration, or a wall-clock time given as
+/// `until 14:30`, `@17:00` or `tomorrow 09:00`
+#[derive(Clone, Copy, Debug)]
+pub enum TimerTarget {
+    In(Duration),
+    At(DateTime<Local>),
+}
+
+impl TimerTarget {
+    /// Time left from `now`, zero for a time that has passed
+    pub fn remaining(&self, now: DateTime<Local>) -> Duration {
+        match self {
+            Self::In(dur) => *dur,
+            Self::At(time) => (*time - now).to_std().unwrap_or_default(),
+        }
+    }
+}
+
+/// Maps a local wall-clock time to an instant: the earlier one when a DST
+/// change repeats it, the first minute after the gap when it skips it
+pub fn resolve_local(time: NaiveDateTime) -> DateTime<Local> {
+    match Local.from_local_datetime(&time) {
+        LocalResult::Single(time) => time,
+        LocalResult::Ambiguous(earliest, _) => earliest,
+        LocalResult::None => (1..=24 * 60)
+            .find_map(|min| {
+                Local
+                    .from_local_datetime(&(time + chrono::Duration::minutes(min)))
+                    .earliest()
+            })
+            .unwrap_or_else(|| Local.from_utc_datetime(&time)),
+    }
+}
+
+pub fn parse_timer_target(text: &str) -> Result<TimerTarget> {
+    let text = text.trim();
+    let lower = text.to_lowercase();
+    let (wall_clock, rest) = match (lower.strip_prefix('@'), lower.strip_prefix("until ")) {
+        (Some(rest), _) | (_, Some(rest)) => (true, rest.trim()),
+        _ => (false, lower.as_str()),
+    };
+    let (day, time) = match rest.split_once(char::is_whitespace) {
+        Some(("today", time)) => (Some(0), time.trim()),
+        Some(("tomorrow", time)) => (Some(1), time.trim()),
+        _ => (None, rest),
     };
+    if !wall_clock && day.is_none() {
+        return parse_duration(text).map(TimerTarget::In);
+    }
 
-    let (secs, _) = match text.split_once('s') {
-        Some((secs, "")) => {
-            let secs = secs.parse::<u64>()?;
-            (Duration::from_secs(secs), text)
-        },
-        None if text == "" => (Duration::ZERO, ""),
-        _ => return Err(PorsmoError::WrongFormatError),
+    let time = NaiveTime::parse_from_str(time, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+        .map_err(|_| PorsmoError::InvalidClockTime(time.to_string()))?;
+    let now = Local::now();
+    let today = now.date_naive();
+    let at = |days| resolve_local((today + chrono::Days::new(days)).and_time(time));
+    let target = match day {
+        Some(days) => at(days),
+        // a time that has passed today means tomorrow
+        None => Some(at(0)).filter(|at| *at > now).unwrap_or_else(|| at(1)),
     };
+    if target <= now {
+        return Err(PorsmoError::TargetInPast(text.to_string()));
+    }
+    Ok(TimerTarget::At(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(template: &str, dur: Duration) -> String {
+        let style: TimeStyle = template.parse().unwrap();
+        style.format(dur)
+    }
+
+    fn error_at(text: &str) -> usize {
+        match parse_duration(text) {
+            Err(PorsmoError::InvalidDuration { position, .. }) => position,
+            other => panic!("`{text}` gave {other:?}"),
+        }
+    }
+
+    #[test]
+    fn durations_parse() {
+        let secs = |text: &str| parse_duration(text).unwrap().as_secs();
+        assert_eq!(secs("25m"), 1500);
+        assert_eq!(secs("1h30m"), 5400);
+        assert_eq!(secs(" 1h 30m "), 5400);
+        assert_eq!(secs("1h30"), 5400);
+        assert_eq!(secs("1.5h"), 5400);
+        assert_eq!(secs("90"), 5400);
+        assert_eq!(secs("2 Days"), 172_800);
+        assert_eq!(secs("1m 30"), 90);
+        assert_eq!(secs("25:00"), 1500);
+        assert_eq!(secs("1:30:00"), 5400);
+        assert_eq!(
+            parse_duration("0:1.5").unwrap(),
+            Duration::from_millis(1500)
+        );
+    }
+
+    #[test]
+    fn duration_errors_point_at_the_culprit() {
+        assert_eq!(error_at(""), 0);
+        assert_eq!(error_at("   "), 0);
+        assert_eq!(error_at("1h x"), 3);
+        assert_eq!(error_at("5q"), 1);
+        assert_eq!(error_at("1.2.3m"), 0);
+        assert_eq!(error_at("30m 1h"), 4);
+        assert_eq!(error_at("1h 2h"), 3);
+        assert_eq!(error_at("30s 5"), 4);
+        // positions count characters, not bytes
+        assert_eq!(error_at("5m ü"), 3);
+        assert_eq!(error_at("25:6x"), 4);
+        assert_eq!(error_at("1:60"), 2);
+        assert_eq!(error_at("1:2:3:4"), 5);
+        assert!(matches!(
+            parse_duration("5q"),
+            Err(PorsmoError::InvalidDuration { reason: DurationProblem::UnknownUnit(unit), .. })
+                if unit == "q"
+        ));
+        assert!(matches!(
+            parse_duration("30m 1h"),
+            Err(PorsmoError::InvalidDuration {
+                reason: DurationProblem::UnitOrder("unit-hours", "unit-minutes"),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn compact_durations_read_back() {
+        for dur in [
+            Duration::ZERO,
+            Duration::from_secs(25 * 60),
+            Duration::from_secs(5400),
+            Duration::from_millis(1500),
+            Duration::from_millis(3_600_250),
+            Duration::from_nanos(1),
+            Duration::new(59, 999_999_999),
+        ] {
+            let text = compact_duration(dur);
+            assert_eq!(parse_duration(&text).unwrap(), dur, "{text}");
+        }
+        assert_eq!(compact_duration(Duration::from_millis(90_500)), "1m30.5s");
+    }
+
+    #[test]
+    fn templates_pad_and_unpad() {
+        let dur = Duration::from_millis(3_725_340);
+        assert_eq!(template("%H:%M:%S", dur), "01:02:05");
+        assert_eq!(template("%hh %mm %ss", dur), "1h 2m 5s");
+        assert_eq!(template("%M:%S.%f", dur), "02:05.34");
+    }
+
+    #[test]
+    fn templates_escape_percent() {
+        let dur = Duration::from_secs(65);
+        assert_eq!(template("%m%% done", dur), "1% done");
+        assert_eq!(template("%%S is %S", dur), "%S is 05");
+        assert_eq!(template("%q %", dur), "%q %");
+    }
+
+    #[test]
+    fn timer_targets_read_durations_and_times_of_day() {
+        assert!(matches!(
+            parse_timer_target("25m"),
+            Ok(TimerTarget::In(dur)) if dur == Duration::from_secs(1500)
+        ));
+        let at = |text: &str| match parse_timer_target(text) {
+            Ok(TimerTarget::At(time)) => time,
+            other => panic!("`{text}` gave {other:?}"),
+        };
+        let now = Local::now();
+        for text in ["@14:30", "until 14:30", "Until 14:30:00", "@ 14:30"] {
+            let time = at(text);
+            assert_eq!(time.time(), NaiveTime::from_hms_opt(14, 30, 0).unwrap());
+            assert!(time > now && time - now <= chrono::Duration::days(1));
+        }
+        let tomorrow = at("tomorrow 09:00");
+        assert_eq!(
+            tomorrow.date_naive(),
+            now.date_naive() + chrono::Days::new(1)
+        );
+
+        assert!(matches!(
+            parse_timer_target("today 00:00"),
+            Err(PorsmoError::TargetInPast(_))
+        ));
+        assert!(matches!(
+            parse_timer_target("@25:00"),
+            Err(PorsmoError::InvalidClockTime(time)) if time == "25:00"
+        ));
+    }
+
+    #[test]
+    fn remaining_time_never_goes_negative() {
+        let now = Local::now();
+        let minute = Duration::from_secs(60);
+        assert_eq!(TimerTarget::In(minute).remaining(now), minute);
+        let soon = TimerTarget::At(now + chrono::Duration::minutes(1));
+        assert_eq!(soon.remaining(now), minute);
+        assert_eq!(
+            soon.remaining(now + chrono::Duration::hours(1)),
+            Duration::ZERO
+        );
+    }
 
-    Ok(hours + mins + secs)
+    #[test]
+    fn ordinary_local_times_resolve_to_themselves() {
+        let noon = NaiveDate::from_ymd_opt(2024, 1, 15)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        assert_eq!(resolve_local(noon).naive_local(), noon);
+    }
 }
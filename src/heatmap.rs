@@ -0,0 +1,193 @@
+use crate::config::settings;
+use crate::history::Record;
+use crate::locale::{count, month, tr, tr_with, weekday as day_name};
+use crate::prelude::*;
+use crate::terminal::heat_color;
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use crossterm::{
+    queue,
+    style::{Print, Stylize},
+};
+use std::collections::HashMap;
+use std::io::Write;
+
+const WEEKS: i64 = 53;
+const CELL: &str = "■";
+/// Used instead of colored cells on terminals without color support
+const SHADES: [char; 5] = ['.', ':', '+', '*', '#'];
+
+/// Scales `count` into 0..=4 relative to the busiest day
+fn level(count: u32, max: u32) -> u8 {
+    if count == 0 || max == 0 {
+        0
+    } else {
+        (count * 4).div_ceil(max).clamp(1, 4) as u8
+    }
+}
+
+fn cell(level: u8, color: bool) -> String {
+    if color {
+        CELL.with(heat_color(level)).to_string()
+    } else {
+        SHADES[level as usize].to_string()
+    }
+}
+
+/// Month names above the first week that starts in that month
+fn month_labels(first_monday: NaiveDate) -> String {
+    let mut labels = String::new();
+    let mut week = 0;
+    while week < WEEKS {
+        let monday = first_monday + Duration::weeks(week);
+        let month_changed = if week == 0 {
+            // only label the partial first month if its name fits before the next
+            (monday + Duration::weeks(4)).month() == monday.month()
+        } else {
+            (monday - Duration::weeks(1)).month() != monday.month()
+        };
+        if month_changed && week + 4 <= WEEKS {
+            labels.push_str(&format!("{:<3} ", month(monday.month0())));
+            week += 4;
+        } else {
+            labels.push(' ');
+            week += 1;
+        }
+    }
+    labels
+}
+
+pub fn heatmap_show(out: &mut impl Write, records: &[Record], color: bool) -> Result<()> {
+    let mut per_day: HashMap<NaiveDate, u32> = HashMap::new();
+    for record in records
+        .iter()
+        .filter(|record| record.is_completed_pomodoro())
+    {
+        *per_day.entry(record.start.date_naive()).or_default() += 1;
+    }
+
+    let today = Local::now().date_naive();
+    let this_monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let first_monday = this_monday - Duration::weeks(WEEKS - 1);
+    let max = per_day
+        .iter()
+        .filter(|(day, _)| **day >= first_monday)
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0);
+    let total: u32 = per_day
+        .iter()
+        .filter(|(day, _)| **day >= first_monday)
+        .map(|(_, count)| *count)
+        .sum();
+
+    let title = tr_with(
+        "heatmap-title",
+        &[("pomodoros", &count(total.into(), "pomodoros"))],
+    );
+    let title = if color {
+        title.with(settings().theme.title).to_string()
+    } else {
+        title
+    };
+    queue!(
+        out,
+        Print(title),
+        Print("\n    "),
+        Print(month_labels(first_monday)),
+        Print("\n"),
+    )?;
+    for weekday in 0..7 {
+        // every other row is labelled so the labels don't crowd
+        let label = if weekday % 2 == 0 {
+            day_name(weekday)
+        } else {
+            ""
+        };
+        queue!(out, Print(format!("{label:<4}")))?;
+        for week in 0..WEEKS {
+            let day = first_monday + Duration::weeks(week) + Duration::days(weekday.into());
+            if day > today {
+                queue!(out, Print(' '))?;
+            } else {
+                let count = per_day.get(&day).copied().unwrap_or(0);
+                queue!(out, Print(cell(level(count, max), color)))?;
+            }
+        }
+        queue!(out, Print("\n"))?;
+    }
+    queue!(out, Print(format!("    {} ", tr("heatmap-less"))))?;
+    for level in 0..=4 {
+        queue!(out, Print(cell(level, color)))?;
+    }
+    queue!(out, Print(format!(" {}\n", tr("heatmap-more"))))?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::RecordMode;
+
+    fn pomodoro(days_ago: i64) -> Record {
+        let start = Local::now() - Duration::days(days_ago);
+        let target = std::time::Duration::from_secs(1500);
+        Record {
+            mode: RecordMode::Work,
+            phase: None,
+            round: None,
+            start,
+            end: start + Duration::minutes(25),
+            target: Some(target),
+            elapsed: target,
+            overtime: std::time::Duration::ZERO,
+            skipped: false,
+            tags: Vec::new(),
+            task: None,
+            project: None,
+        }
+    }
+
+    #[test]
+    fn levels_scale_to_the_busiest_day() {
+        assert_eq!(level(0, 5), 0);
+        assert_eq!(level(3, 0), 0);
+        assert_eq!(level(1, 100), 1);
+        assert_eq!(level(2, 4), 2);
+        assert_eq!(level(3, 4), 3);
+        assert_eq!(level(4, 4), 4);
+    }
+
+    #[test]
+    fn months_are_labelled_where_they_start() {
+        let jan = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let labels = month_labels(jan);
+        assert!(labels.starts_with(&format!("{:<3}  {:<3} ", month(0), month(1))));
+        assert_eq!(labels.chars().count(), WEEKS as usize);
+        // too little of January is left to fit its name
+        let late_jan = NaiveDate::from_ymd_opt(2024, 1, 22).unwrap();
+        assert!(month_labels(late_jan).starts_with(&format!("  {:<3} ", month(1))));
+    }
+
+    #[test]
+    fn cells_shade_without_color() {
+        let records = [pomodoro(0), pomodoro(0), pomodoro(1), pomodoro(400)];
+        let mut out = Vec::new();
+        heatmap_show(&mut out, &records, false).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines[0],
+            tr_with("heatmap-title", &[("pomodoros", &count(3, "pomodoros"))])
+        );
+        let today = Local::now().weekday().num_days_from_monday() as usize;
+        let yesterday = (today + 6) % 7;
+        assert!(lines[2 + today].trim_end().ends_with('#'));
+        let cell = if today == 0 { 52 } else { 53 };
+        assert_eq!(lines[2 + yesterday].chars().nth(4 + cell - 1), Some('+'));
+        assert_eq!(
+            lines[9],
+            format!("    {} .:+*# {}", tr("heatmap-less"), tr("heatmap-more"))
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use crate::locale::{tr_with, warn};
+use crate::pomodoro::Mode;
+use crate::prelude::*;
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum RecordMode {
+    Work,
+    Break,
+    LongBreak,
+    /// A pomodoro phase that is neither work nor a break
+    Other,
+    Timer,
+    Stopwatch,
+}
+
+impl RecordMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Work => "work",
+            Self::Break => "break",
+            Self::LongBreak => "long-break",
+            Self::Other => "other",
+            Self::Timer => "timer",
+            Self::Stopwatch => "stopwatch",
+        }
+    }
+}
+
+impl From<Mode> for RecordMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Work => Self::Work,
+            Mode::Break => Self::Break,
+            Mode::LongBreak => Self::LongBreak,
+            Mode::Other => Self::Other,
+        }
+    }
+}
+
+/// One finished pomodoro phase, timer run or stopwatch run.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Record {
+    pub mode: RecordMode,
+    /// Name of the pomodoro phase, differs from the mode for custom phases
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub round: Option<u32>,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    #[serde(default, with = "secs_opt", skip_serializing_if = "Option::is_none")]
+    pub target: Option<Duration>,
+    #[serde(with = "secs")]
+    pub elapsed: Duration,
+    #[serde(default, with = "secs")]
+    pub overtime: Duration,
+    #[serde(default)]
+    pub skipped: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    /// Project whose `.porsmo.toml` was in effect
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+}
+
+impl Record {
+    /// Whether the record started inside `[since, until)`, missing bounds are open
+    pub fn started_within(
+        &self,
+        since: Option<DateTime<Local>>,
+        until: Option<DateTime<Local>>,
+    ) -> bool {
+        since.is_none_or(|since| self.start >= since)
+            && until.is_none_or(|until| self.start < until)
+    }
+
+    /// Whether this is a work phase that ran to its target without being
+    /// skipped, the only kind of record counted as a pomodoro
+    pub fn is_completed_pomodoro(&self) -> bool {
+        self.mode == RecordMode::Work
+            && !self.skipped
+            && self.target.is_some_and(|target| self.elapsed >= target)
+    }
+}
+
+/// Append-only JSON lines file of [`Record`]s, one record per line.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    path: Option<PathBuf>,
+}
+
+impl History {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path: Some(path) }
+    }
+
+    /// History that silently drops every record
+    pub fn disabled() -> Self {
+        Self { path: None }
+    }
+
+    /// `$XDG_DATA_HOME/porsmo/history.jsonl` (or the platform equivalent)
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("porsmo").join("history.jsonl"))
+    }
+
+    pub fn open_default() -> Self {
+        match Self::default_path() {
+            Some(path) => Self::new(path),
+            None => Self::disabled(),
+        }
+    }
+
+    pub fn append(&self, record: &Record) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(PorsmoError::HistoryIo)?;
+        }
+        let mut line = serde_json::to_string(record).map_err(PorsmoError::HistoryFormat)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(PorsmoError::HistoryIo)
+    }
+
+    /// Every record in the file, lines that can't be read are skipped
+    /// with a warning, e.g. one cut short by a crash while writing it
+    pub fn load(&self) -> Result<Vec<Record>> {
+        let Some(path) = &self.path else {
+            return Ok(Vec::new());
+        };
+        let file = match fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(PorsmoError::HistoryIo(e)),
+        };
+        let mut records = Vec::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(PorsmoError::HistoryIo)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(record) => records.push(record),
+                Err(error) => warn(&tr_with(
+                    "history-bad-line",
+                    &[
+                        ("line", &(number + 1)),
+                        ("file", &path.display()),
+                        ("error", &error),
+                    ],
+                )),
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Runs shorter than this show as zero everywhere and aren't recorded
+const SHORTEST_RECORD: Duration = Duration::from_secs(1);
+
+/// Why the history couldn't be written, set by the first failure
+static WRITE_ERROR: OnceLock<String> = OnceLock::new();
+
+/// The warning for the first record that couldn't be written, if any
+pub fn write_error() -> Option<&'static str> {
+    WRITE_ERROR.get().map(String::as_str)
+}
+
+/// Tracks the wall-clock start of the current run and writes it to the
+/// history once it finishes.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    history: History,
+    started: DateTime<Local>,
+    task: Option<String>,
+    tags: Vec<String>,
+    project: Option<String>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new(History::disabled())
+    }
+}
+
+impl Recorder {
+    pub fn new(history: History) -> Self {
+        Self {
+            history,
+            started: Local::now(),
+            task: None,
+            tags: Vec::new(),
+            project: None,
+        }
+    }
+
+    /// Attaches a task label and tags to every record written from now on
+    pub fn labelled(self, task: Option<String>, tags: Vec<String>) -> Self {
+        Self { task, tags, ..self }
+    }
+
+    /// Records every session as part of `project`
+    pub fn in_project(self, project: Option<String>) -> Self {
+        Self { project, ..self }
+    }
+
+    pub fn project(&self) -> Option<&str> {
+        self.project.as_deref()
+    }
+
+    pub fn restart(&mut self) {
+        self.started = Local::now();
+    }
+
+    /// Writes a record spanning from the last restart until now, then
+    /// restarts the clock for the next run. Runs quit right away aren't
+    /// written, and a history that can't be written only warns so the
+    /// running counter carries on
+    pub fn record(
+        &mut self,
+        mode: RecordMode,
+        phase: Option<&str>,
+        round: Option<u32>,
+        target: Option<Duration>,
+        elapsed: Duration,
+        skipped: bool,
+    ) {
+        let end = Local::now();
+        if elapsed < SHORTEST_RECORD {
+            self.started = end;
+            return;
+        }
+        let record = Record {
+            mode,
+            phase: phase.map(str::to_string),
+            round,
+            start: self.started,
+            end,
+            target,
+            elapsed,
+            overtime: target.map_or(Duration::ZERO, |target| elapsed.saturating_sub(target)),
+            skipped,
+            tags: self.tags.clone(),
+            task: self.task.clone(),
+            project: self.project.clone(),
+        };
+        self.started = end;
+        if let Err(error) = self.history.append(&record) {
+            let error = match std::error::Error::source(&error) {
+                Some(cause) => format!("{error}: {cause}"),
+                None => error.to_string(),
+            };
+            let _ = WRITE_ERROR.set(tr_with("history-write-failed", &[("error", &error)]));
+        }
+    }
+}
+
+mod secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(dur: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_f64((dur.as_secs_f64() * 1000.0).round() / 1000.0)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        let secs = f64::deserialize(d)?;
+        Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom)
+    }
+}
+
+mod secs_opt {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(dur: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        match dur {
+            Some(dur) => super::secs::serialize(dur, s),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+        Option::<f64>::deserialize(d)?
+            .map(|secs| Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(mode: RecordMode, elapsed: u64) -> Record {
+        let start = Local::now();
+        Record {
+            mode,
+            phase: None,
+            round: Some(1),
+            start,
+            end: start + chrono::Duration::seconds(elapsed as i64),
+            target: Some(Duration::from_secs(60)),
+            elapsed: Duration::from_secs(elapsed),
+            overtime: Duration::ZERO,
+            skipped: false,
+            tags: vec!["deep".into()],
+            task: None,
+            project: None,
+        }
+    }
+
+    fn temp_history(name: &str) -> (History, PathBuf) {
+        let path =
+            std::env::temp_dir().join(format!("porsmo-test-{}-{name}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        (History::new(path.clone()), path)
+    }
+
+    #[test]
+    fn load_skips_lines_it_cant_read() {
+        let (history, path) = temp_history("skip");
+        history.append(&record(RecordMode::Work, 60)).unwrap();
+        // a line cut short by a crash, then an empty one
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(b"{\"mode\":\"work\",\"sta\n\n"))
+            .unwrap();
+        history.append(&record(RecordMode::Break, 30)).unwrap();
+
+        let records = history.load().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].mode, RecordMode::Work);
+        assert!(records[0].is_completed_pomodoro());
+        assert_eq!(records[0].tags, ["deep"]);
+        assert_eq!(records[1].mode, RecordMode::Break);
+        assert_eq!(records[1].elapsed, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn missing_and_disabled_histories_are_empty() {
+        let (history, _) = temp_history("missing");
+        assert!(history.load().unwrap().is_empty());
+        let disabled = History::disabled();
+        disabled.append(&record(RecordMode::Work, 60)).unwrap();
+        assert!(disabled.load().unwrap().is_empty());
+    }
+
+    #[test]
+    fn only_finished_unskipped_work_is_a_pomodoro() {
+        assert!(!record(RecordMode::Work, 59).is_completed_pomodoro());
+        assert!(!record(RecordMode::Break, 60).is_completed_pomodoro());
+        let mut skipped = record(RecordMode::Work, 90);
+        skipped.skipped = true;
+        assert!(!skipped.is_completed_pomodoro());
+    }
+}
@@ -0,0 +1,61 @@
+use crate::config::settings;
+use crate::locale::tr_with;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+
+/// When a command from the `[hooks]` section runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hook {
+    PhaseStart,
+    PhaseEnd,
+}
+
+/// Why the first hook that failed did
+static FAILED: OnceLock<String> = OnceLock::new();
+
+/// Set once a hook couldn't be started or exited with an error
+pub fn error() -> Option<&'static str> {
+    FAILED.get().map(String::as_str)
+}
+
+fn shell(command: &str) -> Command {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut shell = Command::new(shell);
+    shell.args([flag, command]);
+    shell
+}
+
+/// Starts the command set for `hook` in the background, `env` is passed to
+/// it with every name prefixed by `PORSMO_`
+pub fn run(hook: Hook, env: &[(&str, String)]) {
+    let Some(command) = settings().hooks.command(hook) else {
+        return;
+    };
+    let mut child = shell(command);
+    child
+        .envs(
+            env.iter()
+                .map(|(name, value)| (format!("PORSMO_{name}"), value)),
+        )
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    let command = command.to_string();
+    // waiting keeps the hook from lingering as a zombie
+    thread::spawn(move || {
+        let error = match child.status() {
+            Ok(status) if status.success() => return,
+            Ok(status) => status.to_string(),
+            Err(error) => error.to_string(),
+        };
+        let _ = FAILED.set(tr_with(
+            "hook-failed",
+            &[("command", &command), ("error", &error)],
+        ));
+    });
+}
@@ -0,0 +1,264 @@
+use crate::history::{Record, RecordMode};
+use crate::locale::count;
+use crate::pomodoro::{default_title, Mode};
+use crate::prelude::*;
+use chrono::{DateTime, Local, Utc};
+use std::io::Write;
+use std::time::Duration;
+
+/// A contiguous stretch of focus time that becomes one VEVENT
+struct Block<'a> {
+    first: &'a Record,
+    end: DateTime<Local>,
+    pomodoros: u32,
+    breaks: u32,
+}
+
+impl Block<'_> {
+    fn summary(&self) -> String {
+        match &self.first.task {
+            Some(task) => task.clone(),
+            None => {
+                let mode = match self.first.mode {
+                    RecordMode::Break => Mode::Break,
+                    RecordMode::LongBreak => Mode::LongBreak,
+                    _ => Mode::Work,
+                };
+                default_title(mode).to_string()
+            }
+        }
+    }
+
+    fn description(&self) -> String {
+        let pomodoros = count(self.pomodoros.into(), "pomodoros");
+        let breaks = count(self.breaks.into(), "breaks");
+        match (self.pomodoros, self.breaks) {
+            (_, 0) => pomodoros,
+            (0, _) => breaks,
+            _ => format!("{pomodoros}, {breaks}"),
+        }
+    }
+}
+
+fn is_break(mode: RecordMode) -> bool {
+    matches!(mode, RecordMode::Break | RecordMode::LongBreak)
+}
+
+/// Groups completed work phases (and breaks if asked for) into calendar
+/// blocks.
+/// With `merge` set, records starting within `merge` of the previous
+/// block's end are folded into it.
+fn blocks<'a>(
+    records: &[&'a Record],
+    include_breaks: bool,
+    merge: Option<Duration>,
+) -> Vec<Block<'a>> {
+    let mut records: Vec<&Record> = records
+        .iter()
+        .copied()
+        .filter(|record| {
+            record.is_completed_pomodoro() || (include_breaks && is_break(record.mode))
+        })
+        .collect();
+    records.sort_by_key(|record| record.start);
+
+    let mut blocks: Vec<Block> = Vec::new();
+    for record in records {
+        let work = record.mode == RecordMode::Work;
+        let gap = chrono::Duration::from_std(merge.unwrap_or_default()).unwrap_or_default();
+        match blocks.last_mut() {
+            Some(block) if merge.is_some() && record.start <= block.end + gap => {
+                block.end = block.end.max(record.end);
+                if work {
+                    block.pomodoros += 1;
+                } else {
+                    block.breaks += 1;
+                }
+            }
+            _ => blocks.push(Block {
+                first: record,
+                end: record.end,
+                pomodoros: work as u32,
+                breaks: !work as u32,
+            }),
+        }
+    }
+    blocks
+}
+
+/// Escapes TEXT values as described in RFC 5545 section 3.3.11
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace(['\r', '\n'], "\\n")
+}
+
+fn ical_time(time: &DateTime<Local>) -> String {
+    time.with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// Writes a content line, folding it at 75 octets
+fn write_line(out: &mut impl Write, line: &str) -> Result<()> {
+    let mut width = 0;
+    for ch in line.chars() {
+        if width + ch.len_utf8() > 75 {
+            out.write_all(b"\r\n ")?;
+            width = 1;
+        }
+        write!(out, "{ch}")?;
+        width += ch.len_utf8();
+    }
+    out.write_all(b"\r\n")?;
+    Ok(())
+}
+
+pub fn write_ical(
+    out: &mut impl Write,
+    records: &[&Record],
+    include_breaks: bool,
+    merge: Option<Duration>,
+) -> Result<()> {
+    let stamp = ical_time(&Local::now());
+    write_line(out, "BEGIN:VCALENDAR")?;
+    write_line(out, "VERSION:2.0")?;
+    write_line(out, "PRODID:-//porsmo//porsmo//EN")?;
+    for block in blocks(records, include_breaks, merge) {
+        let start = ical_time(&block.first.start);
+        write_line(out, "BEGIN:VEVENT")?;
+        write_line(
+            out,
+            &format!("UID:{start}-{}@porsmo", block.first.mode.name()),
+        )?;
+        write_line(out, &format!("DTSTAMP:{stamp}"))?;
+        write_line(out, &format!("DTSTART:{start}"))?;
+        write_line(out, &format!("DTEND:{}", ical_time(&block.end)))?;
+        write_line(out, &format!("SUMMARY:{}", escape_text(&block.summary())))?;
+        write_line(
+            out,
+            &format!("DESCRIPTION:{}", escape_text(&block.description())),
+        )?;
+        if !block.first.tags.is_empty() {
+            let tags: Vec<String> = block
+                .first
+                .tags
+                .iter()
+                .map(|tag| escape_text(tag))
+                .collect();
+            write_line(out, &format!("CATEGORIES:{}", tags.join(",")))?;
+        }
+        write_line(out, "END:VEVENT")?;
+    }
+    write_line(out, "END:VCALENDAR")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn work(task: &str, tags: &[&str]) -> Record {
+        let start = Local.with_ymd_and_hms(2026, 10, 16, 9, 0, 0).unwrap();
+        let target = Duration::from_secs(25 * 60);
+        Record {
+            mode: RecordMode::Work,
+            phase: None,
+            round: Some(1),
+            start,
+            end: start + chrono::Duration::minutes(25),
+            target: Some(target),
+            elapsed: target,
+            overtime: Duration::ZERO,
+            skipped: false,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            task: Some(task.to_string()),
+            project: None,
+        }
+    }
+
+    fn ical(records: &[Record]) -> String {
+        let records: Vec<&Record> = records.iter().collect();
+        let mut out = Vec::new();
+        write_ical(&mut out, &records, false, None).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(escape_text("plain"), "plain");
+        assert_eq!(escape_text(r"a\b"), r"a\\b");
+        assert_eq!(escape_text("a, b; c"), r"a\, b\; c");
+        assert_eq!(
+            escape_text("one\ntwo\r\nthree\rfour"),
+            r"one\ntwo\nthree\nfour"
+        );
+    }
+
+    #[test]
+    fn long_lines_fold_between_characters() {
+        let mut out = Vec::new();
+        write_line(&mut out, &"x".repeat(80)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{}\r\n {}\r\n", "x".repeat(75), "x".repeat(5))
+        );
+
+        // two octets each, a fold never splits one
+        let mut out = Vec::new();
+        write_line(&mut out, &"é".repeat(40)).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.split("\r\n").collect();
+        assert_eq!(
+            lines,
+            ["é".repeat(37), format!(" {}", "é".repeat(3)), String::new()]
+        );
+    }
+
+    #[test]
+    fn events_escape_summary_and_categories() {
+        let text = ical(&[work("write; review, ship", &["a,b", "c;d"])]);
+        assert!(
+            text.contains("SUMMARY:write\\; review\\, ship\r\n"),
+            "{text}"
+        );
+        assert!(text.contains("CATEGORIES:a\\,b,c\\;d\r\n"), "{text}");
+        let description = format!("DESCRIPTION:{}\r\n", count(1, "pomodoros"));
+        assert!(text.contains(&description), "{text}");
+    }
+
+    #[test]
+    fn unfinished_work_is_left_out() {
+        let mut skipped = work("skipped", &[]);
+        skipped.skipped = true;
+        let mut quit = work("quit", &[]);
+        quit.elapsed = Duration::from_secs(60);
+        assert!(!ical(&[skipped, quit]).contains("BEGIN:VEVENT"));
+    }
+
+    #[test]
+    fn nearby_phases_merge_into_one_block() {
+        let first = work("write", &[]);
+        let mut rest = first.clone();
+        rest.mode = RecordMode::Break;
+        rest.start = first.end;
+        rest.end = rest.start + chrono::Duration::minutes(5);
+        let mut second = first.clone();
+        second.start = rest.end + chrono::Duration::minutes(2);
+        second.end = second.start + chrono::Duration::minutes(25);
+        let records = [&first, &rest, &second];
+
+        let merged = blocks(&records, true, Some(Duration::from_secs(5 * 60)));
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].end, second.end);
+        assert_eq!(
+            merged[0].description(),
+            format!("{}, {}", count(2, "pomodoros"), count(1, "breaks"))
+        );
+        assert_eq!(blocks(&records, false, None).len(), 2);
+        assert_eq!(blocks(&records, true, None).len(), 3);
+    }
+}
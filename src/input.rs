@@ -1,8 +1,12 @@
+use crate::config::settings;
+use crate::locale::{tr, tr_with};
 use crate::prelude::*;
+use std::str::FromStr;
 use std::time::Duration;
 
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Command {
     Quit,
     Pause,
@@ -13,6 +17,14 @@ pub enum Command {
     Skip,
     Yes,
     No,
+    VolumeUp,
+    VolumeDown,
+    /// Silences a ringing alarm
+    Acknowledge,
+    /// The terminal changed size
+    Resize,
+    /// No input came in before the tick ran out
+    Tick,
     Invalid,
 }
 
@@ -20,6 +32,7 @@ impl From<Event> for Command {
     fn from(event: Event) -> Self {
         match event {
             Event::Key(key) => Command::from(key),
+            Event::Resize(..) => Command::Resize,
             _ => Command::Invalid,
         }
     }
@@ -27,82 +40,191 @@ impl From<Event> for Command {
 
 impl From<KeyEvent> for Command {
     fn from(key: KeyEvent) -> Self {
-        match key {
-            KeyEvent {
-                code: KeyCode::Char('q'),
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::NONE,
-                ..
-            } => Self::Quit,
-            KeyEvent {
-                code: KeyCode::Char('c'),
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::CONTROL,
-                ..
-            } => Self::Quit,
-            KeyEvent {
-                code: KeyCode::Char('z'),
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::CONTROL,
-                ..
-            } => Self::Quit,
-            KeyEvent {
-                code: KeyCode::Char(' '),
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::NONE,
-                ..
-            } => Self::Toggle,
-            KeyEvent {
-                code: KeyCode::Enter,
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::NONE,
-                ..
-            } => Self::Enter,
-            KeyEvent {
-                code: KeyCode::Char('S'),
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::SHIFT,
-                ..
-            } => Self::Skip,
-            KeyEvent {
-                code: KeyCode::Char('y'),
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::NONE,
-                ..
-            } => Self::Yes,
-            KeyEvent {
-                code: KeyCode::Char('n'),
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::NONE,
-                ..
-            } => Self::No,
-            KeyEvent {
-                code: KeyCode::Char('t'),
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::NONE,
-                ..
-            } => Self::Toggle,
-            KeyEvent {
-                code: KeyCode::Char('p'),
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::NONE,
-                ..
-            } => Self::Pause,
-            KeyEvent {
-                code: KeyCode::Char('c'),
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::NONE,
-                ..
-            } => Self::Resume,
-            KeyEvent {
-                code: KeyCode::Char('r'),
-                kind: KeyEventKind::Press,
-                modifiers: KeyModifiers::NONE,
-                ..
-            } => Self::Reset,
-            _ => Self::Invalid,
+        settings().keymap.command(key)
+    }
+}
+
+/// A key with its modifiers, letters carry shift in their case
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => Self {
+                code: KeyCode::Char(c.to_ascii_uppercase()),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            _ => Self { code, modifiers },
         }
     }
+
+    /// How the key is shown in hints, such as `Q`, `Shift S` or `Ctrl C`
+    pub fn label(&self) -> String {
+        let key = match self.code {
+            KeyCode::Char(' ') => tr("key-space").to_string(),
+            KeyCode::Char(c) if c.is_uppercase() => format!("Shift {c}"),
+            KeyCode::Char(c) => c.to_uppercase().to_string(),
+            KeyCode::Enter => tr("key-enter").to_string(),
+            KeyCode::Esc => "Esc".into(),
+            KeyCode::Tab => "Tab".into(),
+            KeyCode::Backspace => "Backspace".into(),
+            KeyCode::Up => "↑".into(),
+            KeyCode::Down => "↓".into(),
+            KeyCode::Left => "←".into(),
+            KeyCode::Right => "→".into(),
+            code => format!("{code:?}"),
+        };
+        let mut label = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            label.push_str("Ctrl ");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            label.push_str("Alt ");
+        }
+        label + &key
+    }
+}
+
+impl From<KeyEvent> for KeyBinding {
+    fn from(key: KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = PorsmoError;
+
+    /// Keys like `q`, `S`, `space`, `enter`, `ctrl+c` or `shift+s`
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || PorsmoError::InvalidKey(s.to_string());
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = s.split('+').collect();
+        let key = match parts.pop() {
+            // "ctrl++" binds the plus key
+            Some("") if s.ends_with("++") || s == "+" => {
+                parts.pop();
+                "+"
+            }
+            Some(key) => key,
+            None => return Err(invalid()),
+        };
+        for modifier in parts {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(invalid()),
+            };
+        }
+        let code = match key.to_ascii_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            _ => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => return Err(invalid()),
+                }
+            }
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct KeyMap(Vec<(KeyBinding, Command)>);
+
+/// Built-in key bindings, in the syntax of the config file
+pub const DEFAULT_KEYS: [(Command, &[&str]); 12] = [
+    (Command::Quit, &["q", "ctrl+c", "ctrl+z"]),
+    (Command::Toggle, &["space", "t"]),
+    (Command::Pause, &["p"]),
+    (Command::Resume, &["c"]),
+    (Command::Reset, &["r"]),
+    (Command::Enter, &["enter"]),
+    (Command::Skip, &["shift+s"]),
+    (Command::Yes, &["y"]),
+    (Command::No, &["n"]),
+    (Command::VolumeUp, &["+", "="]),
+    (Command::VolumeDown, &["-"]),
+    (Command::Acknowledge, &["a"]),
+];
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self(
+            DEFAULT_KEYS
+                .iter()
+                .flat_map(|(command, keys)| {
+                    keys.iter().map(|key| {
+                        let key = key.parse().expect("built-in key bindings are valid");
+                        (key, *command)
+                    })
+                })
+                .collect(),
+        )
+    }
+}
+
+impl KeyMap {
+    pub fn command(&self, key: KeyEvent) -> Command {
+        if key.kind != KeyEventKind::Press {
+            return Command::Invalid;
+        }
+        let pressed = KeyBinding::from(key);
+        self.0
+            .iter()
+            .find(|(binding, _)| *binding == pressed)
+            .map_or(Command::Invalid, |(_, command)| *command)
+    }
+
+    /// The first key bound to `command`
+    pub fn key(&self, command: Command) -> Option<KeyBinding> {
+        self.0
+            .iter()
+            .find(|(_, bound)| *bound == command)
+            .map(|(key, _)| *key)
+    }
+
+    /// Replaces every binding of `command` with `keys`
+    pub fn bind(&mut self, command: Command, keys: &[KeyBinding]) {
+        self.0.retain(|(_, bound)| *bound != command);
+        self.0.extend(keys.iter().map(|key| (*key, command)));
+    }
+}
+
+/// Hints such as `[Q]: quit` from the first key bound to each command of
+/// a group, groups without any bound key are left out
+pub fn hints(groups: &[(&[Command], &'static str)]) -> Vec<String> {
+    let keymap = &settings().keymap;
+    groups
+        .iter()
+        .filter_map(|(commands, action)| {
+            let keys: Vec<String> = commands
+                .iter()
+                .filter_map(|command| keymap.key(*command))
+                .map(|key| key.label())
+                .collect();
+            if keys.is_empty() {
+                return None;
+            }
+            Some(tr_with(
+                "hint",
+                &[("keys", &keys.join("/")), ("action", &tr(action))],
+            ))
+        })
+        .collect()
 }
 
 #[allow(dead_code)]
@@ -112,12 +234,17 @@ impl Iterator for CommandIter {
     type Item = Result<Command>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        Some(get_event(TIMEOUT).transpose()?.map(Command::from))
+        Some(get_event(tick()).transpose()?.map(Command::from))
     }
 }
 
 pub const TIMEOUT: Duration = Duration::from_millis(250);
 
+/// How long to wait for input before redrawing
+pub fn tick() -> Duration {
+    settings().tick
+}
+
 pub fn get_event(timeout: Duration) -> Result<Option<event::Event>> {
     if event::poll(timeout)? {
         Ok(Some(event::read()?))
@@ -125,3 +252,107 @@ pub fn get_event(timeout: Duration) -> Result<Option<event::Event>> {
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyBinding {
+        KeyBinding::new(code, modifiers)
+    }
+
+    #[test]
+    fn key_bindings_parse() {
+        let parsed = |text: &str| text.parse::<KeyBinding>().unwrap();
+        assert_eq!(parsed("q"), key(KeyCode::Char('q'), KeyModifiers::NONE));
+        assert_eq!(parsed("S"), key(KeyCode::Char('S'), KeyModifiers::NONE));
+        assert_eq!(parsed("shift+s"), parsed("S"));
+        assert_eq!(
+            parsed("Ctrl+c"),
+            key(KeyCode::Char('c'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            parsed("ctrl+alt+x"),
+            key(
+                KeyCode::Char('x'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            )
+        );
+        assert_eq!(parsed("space"), key(KeyCode::Char(' '), KeyModifiers::NONE));
+        assert_eq!(parsed("Return"), key(KeyCode::Enter, KeyModifiers::NONE));
+        assert_eq!(parsed("+"), key(KeyCode::Char('+'), KeyModifiers::NONE));
+        assert_eq!(
+            parsed("ctrl++"),
+            key(KeyCode::Char('+'), KeyModifiers::CONTROL)
+        );
+    }
+
+    #[test]
+    fn bad_key_bindings_are_rejected() {
+        for text in ["", "ctrl+", "super+q", "qq", "shift+f13"] {
+            assert!(text.parse::<KeyBinding>().is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn default_keys_parse_and_never_clash() {
+        let keymap = KeyMap::default();
+        for (binding, command) in &keymap.0 {
+            let clashing = keymap
+                .0
+                .iter()
+                .find(|(other, bound)| other == binding && bound != command);
+            assert_eq!(clashing, None, "{binding:?} is bound to {command:?}");
+        }
+        let press = |code, modifiers| keymap.command(KeyEvent::new(code, modifiers));
+        assert_eq!(
+            press(KeyCode::Char('S'), KeyModifiers::SHIFT),
+            Command::Skip
+        );
+        assert_eq!(
+            press(KeyCode::Char('s'), KeyModifiers::NONE),
+            Command::Invalid
+        );
+        assert_eq!(
+            press(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            Command::Quit
+        );
+        assert_eq!(
+            press(KeyCode::Char(' '), KeyModifiers::NONE),
+            Command::Toggle
+        );
+        assert_eq!(
+            press(KeyCode::Char('='), KeyModifiers::NONE),
+            Command::VolumeUp
+        );
+    }
+
+    #[test]
+    fn rebinding_replaces_keys_and_labels_follow() {
+        let parsed = |text: &str| text.parse::<KeyBinding>().unwrap();
+        let mut keymap = KeyMap::default();
+        assert_eq!(
+            keymap.key(Command::Skip).map(|key| key.label()),
+            Some("Shift S".into())
+        );
+        keymap.bind(Command::Quit, &[parsed("x")]);
+        keymap.bind(Command::Reset, &[]);
+        keymap.bind(Command::Toggle, &[parsed("ctrl+p"), parsed("space")]);
+        let press = |code| keymap.command(KeyEvent::new(code, KeyModifiers::NONE));
+        assert_eq!(press(KeyCode::Char('q')), Command::Invalid);
+        assert_eq!(press(KeyCode::Char('x')), Command::Quit);
+        assert_eq!(
+            keymap.key(Command::Quit).map(|key| key.label()),
+            Some("X".into())
+        );
+        assert_eq!(keymap.key(Command::Reset), None);
+        assert_eq!(
+            keymap.key(Command::Toggle).map(|key| key.label()),
+            Some("Ctrl P".into())
+        );
+        assert_eq!(
+            parsed("alt+enter").label(),
+            format!("Alt {}", tr("key-enter"))
+        );
+    }
+}
@@ -0,0 +1,530 @@
+use crate::alert::volume_notice;
+use crate::audio::error_notice;
+use crate::bigtext::clock_lines;
+use crate::config::settings;
+use crate::history::write_error;
+use crate::hooks;
+use crate::locale::{tr, tr_with};
+use crate::prelude::*;
+use crate::terminal::supports_color;
+use crossterm::{
+    cursor::{MoveTo, MoveToNextLine},
+    queue,
+    style::{Color, Print, Stylize},
+    terminal::{self, Clear, ClearType},
+};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Narrowest frame content that still fits the controls and progress bar
+pub const MIN_WIDTH: usize = 30;
+/// Frames don't grow past this on wide terminals
+pub const MAX_WIDTH: usize = 64;
+
+/// Columns `text` takes up on screen, CJK characters and most emoji
+/// take two
+pub fn text_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// The longest start of `text` that fits in `width` columns
+fn take_width(text: &str, width: usize) -> &str {
+    let mut used = 0;
+    for (i, ch) in text.char_indices() {
+        used += ch.width().unwrap_or(0);
+        if used > width {
+            return &text[..i];
+        }
+    }
+    text
+}
+
+/// `text` cut down to `width` columns, with an ellipsis if anything was cut
+pub fn truncate(text: &str, width: usize) -> String {
+    if text_width(text) <= width {
+        return text.to_string();
+    }
+    let ellipsis = settings().borders.ellipsis();
+    match width.checked_sub(text_width(ellipsis)) {
+        Some(room) => format!("{}{ellipsis}", take_width(text, room)),
+        None => take_width(text, width).to_string(),
+    }
+}
+
+/// `text` broken into lines of at most `width` columns, between words
+/// where possible
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let gap = usize::from(!line.is_empty());
+        if text_width(&line) + gap + text_width(word) <= width {
+            if gap == 1 {
+                line.push(' ');
+            }
+            line.push_str(word);
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        // words wider than a line, such as CJK text without spaces, are split
+        let mut rest = word;
+        while text_width(rest) > width {
+            let head = match take_width(rest, width) {
+                "" => &rest[..rest.chars().next().map_or(0, char::len_utf8)],
+                head => head,
+            };
+            lines.push(head.to_string());
+            rest = &rest[head.len()..];
+        }
+        line.push_str(rest);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+}
+
+/// Parts of a counter screen, drawn in the order they're configured
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Panel {
+    Title,
+    Clock,
+    Progress,
+    Controls,
+    Footer,
+}
+
+/// Ways to tell a running clock from a paused one besides its color
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PauseCue {
+    /// ▶ while running, ⏸ while paused
+    Icon,
+    /// PAUSED under the clock
+    Label,
+    /// The clock blinks while paused
+    Blink,
+}
+
+pub const DEFAULT_PANELS: [Panel; 5] = [
+    Panel::Title,
+    Panel::Clock,
+    Panel::Progress,
+    Panel::Controls,
+    Panel::Footer,
+];
+
+/// What a counter screen shows, laid out by [`View::draw`] into panels
+#[derive(Default)]
+pub struct View<'a> {
+    pub title: &'a str,
+    pub title_color: Option<Color>,
+    pub clock: Option<(String, Color)>,
+    /// Whether the clock is stopped, shown with the configured cues
+    pub paused: bool,
+    /// Line under the clock, such as the end time
+    pub detail: Option<String>,
+    pub progress: Option<(f64, Color)>,
+    /// Key hints, see [`hints`](crate::input::hints)
+    pub controls: Vec<String>,
+    pub footer: Vec<String>,
+}
+
+impl View<'_> {
+    /// The icon and label cues for the clock, if any are configured
+    fn status(&self) -> Option<String> {
+        let cues = &settings().pause_cues;
+        let borders = settings().borders;
+        let mut status = Vec::new();
+        if cues.contains(&PauseCue::Icon) {
+            status.push(if self.paused {
+                borders.pause()
+            } else {
+                borders.play()
+            });
+        }
+        if self.paused && cues.contains(&PauseCue::Label) {
+            status.push(tr("paused"));
+        }
+        (!status.is_empty()).then(|| status.join(" "))
+    }
+
+    fn frame(&self, panels: &[Panel]) -> Frame {
+        let cues = &settings().pause_cues;
+        let mut frame = Frame::fit();
+        for (i, panel) in panels.iter().enumerate() {
+            match panel {
+                Panel::Title => {
+                    frame.center(self.title, self.title_color);
+                    if i + 1 < panels.len() {
+                        frame.separator();
+                    }
+                }
+                Panel::Clock => {
+                    frame.blank();
+                    if let Some((clock, color)) = &self.clock {
+                        let lines = frame.clock(clock, *color);
+                        if self.paused && cues.contains(&PauseCue::Blink) && blink_off() {
+                            frame.blank_last(lines);
+                        }
+                        let detail = [self.status(), self.detail.clone()]
+                            .into_iter()
+                            .flatten()
+                            .collect::<Vec<_>>()
+                            .join("  ");
+                        frame.center(&detail, Some(settings().theme.detail));
+                    }
+                }
+                Panel::Progress => {
+                    if let Some((ratio, color)) = self.progress {
+                        frame.progress(ratio, color).blank();
+                    }
+                }
+                Panel::Controls => {
+                    // the title brings its own separator
+                    if i > 0 && panels[i - 1] != Panel::Title {
+                        frame.separator();
+                    }
+                    frame.controls(&self.controls);
+                }
+                Panel::Footer => {
+                    for line in &self.footer {
+                        frame.wrapped(line, None);
+                    }
+                    if let Some(volume) = volume_notice() {
+                        frame.wrapped(&volume, Some(settings().theme.detail));
+                    }
+                    if let Some(error) = error_notice() {
+                        frame.wrapped(&error, Some(settings().theme.notice));
+                    }
+                    if let Some(error) = write_error() {
+                        frame.wrapped(error, Some(settings().theme.notice));
+                    }
+                    if let Some(error) = hooks::error() {
+                        frame.wrapped(error, Some(settings().theme.notice));
+                    }
+                }
+            }
+        }
+        frame
+    }
+
+    /// Draws the configured panels centered in the terminal
+    pub fn draw(&self, out: &mut impl Write) -> Result<()> {
+        self.frame(&settings().panels).draw(out)
+    }
+}
+
+/// Whether a blinking clock is in its hidden half second
+fn blink_off() -> bool {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    millis / 500 % 2 == 1
+}
+
+/// A piece of a line with its own color
+struct Segment {
+    text: String,
+    color: Option<Color>,
+}
+
+enum Row {
+    Separator,
+    Content(Vec<Segment>, Align),
+}
+
+/// A bordered box of lines, sized from the terminal and drawn centered in it
+pub struct Frame {
+    width: usize,
+    rows: Vec<Row>,
+}
+
+impl Frame {
+    /// An empty frame `width` columns wide inside the borders
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            rows: Vec::new(),
+        }
+    }
+
+    /// An empty frame as wide as the terminal allows
+    pub fn fit() -> Self {
+        let (cols, _) = terminal::size().unwrap_or((80, 24));
+        Self::new(
+            (cols as usize)
+                .saturating_sub(2)
+                .clamp(MIN_WIDTH, MAX_WIDTH),
+        )
+    }
+
+    /// Lines including both borders
+    pub fn height(&self) -> usize {
+        self.rows.len() + 2
+    }
+
+    pub fn separator(&mut self) -> &mut Self {
+        self.rows.push(Row::Separator);
+        self
+    }
+
+    pub fn blank(&mut self) -> &mut Self {
+        self.line("", None)
+    }
+
+    /// Left aligned text
+    pub fn line(&mut self, text: &str, color: Option<Color>) -> &mut Self {
+        self.segments(vec![(text.to_string(), color)], Align::Left)
+    }
+
+    /// Left aligned text, wrapped onto as many lines as it needs
+    pub fn wrapped(&mut self, text: &str, color: Option<Color>) -> &mut Self {
+        for line in wrap(text, self.width) {
+            self.line(&line, color);
+        }
+        self
+    }
+
+    /// Centered text
+    pub fn center(&mut self, text: &str, color: Option<Color>) -> &mut Self {
+        self.segments(vec![(text.to_string(), color)], Align::Center)
+    }
+
+    /// A line made of differently colored pieces
+    pub fn segments(&mut self, segments: Vec<(String, Option<Color>)>, align: Align) -> &mut Self {
+        let segments = segments
+            .into_iter()
+            .map(|(text, color)| Segment { text, color })
+            .collect();
+        self.rows.push(Row::Content(segments, align));
+        self
+    }
+
+    /// The time, centered and drawn with big digits when enabled and it
+    /// fits, returns the number of lines it took
+    pub fn clock(&mut self, text: &str, color: Color) -> usize {
+        let lines = clock_lines(text, self.width);
+        for line in &lines {
+            self.center(line, Some(color));
+        }
+        lines.len()
+    }
+
+    /// Blanks the last `count` lines, keeping the frame's height
+    pub fn blank_last(&mut self, count: usize) -> &mut Self {
+        let start = self.rows.len().saturating_sub(count);
+        for row in &mut self.rows[start..] {
+            *row = Row::Content(Vec::new(), Align::Left);
+        }
+        self
+    }
+
+    /// `[█████-----]  50%`, scaled to the frame width, the filled cells
+    /// follow the theme's gradient when it has one
+    pub fn progress(&mut self, ratio: f64, color: Color) -> &mut Self {
+        let theme = &settings().theme;
+        let block = settings().borders.block().to_string();
+        let bar_width = self.width * 3 / 5;
+        let ratio = ratio.clamp(0.0, 1.0);
+        let filled = ((ratio * bar_width as f64).round() as usize).min(bar_width);
+        let percent = (ratio * 100.0).round() as usize;
+        let mut segments = vec![("[".to_string(), None)];
+        match theme.gradient {
+            Some(_) => segments.extend((0..filled).map(|cell| {
+                let at = cell as f64 / bar_width.saturating_sub(1).max(1) as f64;
+                (block.clone(), theme.gradient_at(at))
+            })),
+            None => segments.push((block.repeat(filled), Some(color))),
+        }
+        segments.extend([
+            ("-".repeat(bar_width - filled), Some(theme.bar)),
+            ("] ".into(), None),
+            (format!("{percent:>3}%"), Some(theme.percent)),
+        ]);
+        self.segments(segments, Align::Center)
+    }
+
+    /// Key hints separated by commas, wrapped onto as many lines as they need
+    pub fn controls(&mut self, controls: &[String]) -> &mut Self {
+        let mut line = String::new();
+        for part in controls {
+            if !line.is_empty() && text_width(&line) + 2 + text_width(part) > self.width {
+                self.line(&line, Some(settings().theme.controls));
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push_str(", ");
+            }
+            line.push_str(part);
+        }
+        self.line(&line, Some(settings().theme.controls))
+    }
+
+    fn queue_row(&self, out: &mut impl Write, row: &Row, color: bool) -> Result<()> {
+        let side = settings().borders.vertical();
+        let (segments, align) = match row {
+            Row::Separator => return self.queue_border(out, side, side),
+            Row::Content(segments, align) => (segments, *align),
+        };
+        let len: usize = segments.iter().map(|seg| text_width(&seg.text)).sum();
+        let pad_left = match align {
+            Align::Left => 0,
+            Align::Center => self.width.saturating_sub(len) / 2,
+        };
+        queue!(out, Print(side), Print(" ".repeat(pad_left)))?;
+        let mut room = self.width - pad_left;
+        for segment in segments {
+            let text = truncate(&segment.text, room);
+            room -= text_width(&text);
+            match segment.color.filter(|_| color) {
+                Some(color) => queue!(out, Print(text.with(color)))?,
+                None => queue!(out, Print(text))?,
+            }
+        }
+        queue!(out, Print(" ".repeat(room)), Print(side))?;
+        Ok(())
+    }
+
+    fn queue_border(&self, out: &mut impl Write, left: char, right: char) -> Result<()> {
+        let line = settings()
+            .borders
+            .horizontal()
+            .to_string()
+            .repeat(self.width);
+        queue!(out, Print(format!("{left}{line}{right}")))?;
+        Ok(())
+    }
+
+    /// Writes the frame as plain lines, for output outside the TUI
+    pub fn print(&self, out: &mut impl Write) -> Result<()> {
+        let [top_left, top_right, bottom_left, bottom_right] = settings().borders.corners();
+        let color = supports_color();
+        self.queue_border(out, top_left, top_right)?;
+        for row in &self.rows {
+            queue!(out, Print("\n"))?;
+            self.queue_row(out, row, color)?;
+        }
+        queue!(out, Print("\n"))?;
+        self.queue_border(out, bottom_left, bottom_right)?;
+        queue!(out, Print("\n"))?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Draws the frame centered in the terminal, or a notice if it doesn't fit
+    pub fn draw(&self, out: &mut impl Write) -> Result<()> {
+        let (cols, rows) = terminal::size().unwrap_or((80, 24));
+        let (cols, rows) = (cols as usize, rows as usize);
+        if cols < self.width + 2 || rows < self.height() {
+            return too_small(out, cols, rows, self.width + 2, self.height());
+        }
+        let left = " ".repeat((cols - self.width - 2) / 2);
+        let top = (rows - self.height()) / 2;
+        let [top_left, top_right, bottom_left, bottom_right] = settings().borders.corners();
+        let color = settings().color;
+
+        queue!(out, MoveTo(0, 0))?;
+        for _ in 0..top {
+            queue!(out, Clear(ClearType::CurrentLine), MoveToNextLine(1))?;
+        }
+        queue!(out, Print(&left))?;
+        self.queue_border(out, top_left, top_right)?;
+        queue!(out, Clear(ClearType::UntilNewLine), MoveToNextLine(1))?;
+        for row in &self.rows {
+            queue!(out, Print(&left))?;
+            self.queue_row(out, row, color)?;
+            queue!(out, Clear(ClearType::UntilNewLine), MoveToNextLine(1))?;
+        }
+        queue!(out, Print(&left))?;
+        self.queue_border(out, bottom_left, bottom_right)?;
+        queue!(
+            out,
+            Clear(ClearType::UntilNewLine),
+            MoveToNextLine(1),
+            Clear(ClearType::FromCursorDown),
+        )?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Replaces the frame when the terminal can't hold it
+fn too_small(
+    out: &mut impl Write,
+    cols: usize,
+    rows: usize,
+    need_cols: usize,
+    need_rows: usize,
+) -> Result<()> {
+    let lines = [
+        tr("terminal-too-small").to_string(),
+        tr_with(
+            "terminal-size",
+            &[
+                ("need", &format!("{need_cols}x{need_rows}")),
+                ("have", &format!("{cols}x{rows}")),
+            ],
+        ),
+    ];
+    let top = rows.saturating_sub(lines.len()) / 2;
+    for y in 0..rows {
+        queue!(out, MoveTo(0, y as u16), Clear(ClearType::CurrentLine))?;
+        if let Some(line) = y.checked_sub(top).and_then(|i| lines.get(i)) {
+            let line = truncate(line, cols);
+            let left = cols.saturating_sub(text_width(&line)) / 2;
+            queue!(out, Print(" ".repeat(left)))?;
+            if settings().color {
+                queue!(out, Print(line.with(settings().theme.notice)))?;
+            } else {
+                queue!(out, Print(line))?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_counts_columns() {
+        assert_eq!(truncate("hello", 5), "hello");
+        assert_eq!(truncate("hello world", 8), "hello w…");
+        // wide characters that don't fit whole are dropped
+        assert_eq!(truncate("日本語テキスト", 8), "日本語…");
+        assert_eq!(truncate("🍅🍅🍅", 4), "🍅…");
+        assert_eq!(truncate("🍅🍅🍅", 0), "");
+    }
+
+    #[test]
+    fn wrap_breaks_between_words() {
+        assert_eq!(wrap("the quick brown fox", 10), ["the quick", "brown fox"]);
+        assert_eq!(wrap("", 10), [""]);
+    }
+
+    #[test]
+    fn wrap_splits_wide_text() {
+        assert_eq!(wrap("日本語のテキスト", 6), ["日本語", "のテキ", "スト"]);
+        assert_eq!(
+            wrap("🍅 tomato 🍅🍅🍅", 5),
+            ["🍅", "tomat", "o", "🍅🍅", "🍅"]
+        );
+        // a character wider than the line still gets a line of its own
+        assert_eq!(wrap("日本", 1), ["日", "本"]);
+    }
+}
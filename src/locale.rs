@@ -0,0 +1,148 @@
+use crate::config::settings;
+use crate::prelude::*;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// Message catalogs that ship with porsmo, English first
+const CATALOGS: [(&str, &str); 3] = [
+    ("en", include_str!("../locales/en.toml")),
+    ("de", include_str!("../locales/de.toml")),
+    ("fr", include_str!("../locales/fr.toml")),
+];
+
+/// User facing messages in one language
+#[derive(Clone, Debug)]
+pub struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+fn parse(source: &str) -> toml::Table {
+    toml::from_str(source).expect("built-in catalogs are valid")
+}
+
+impl Catalog {
+    /// The catalog for `lang`, its missing messages filled in from English
+    pub fn new(lang: &str) -> Result<Self> {
+        let Some((_, source)) = CATALOGS.iter().find(|(name, _)| *name == lang) else {
+            let known: Vec<_> = CATALOGS.iter().map(|(name, _)| *name).collect();
+            return Err(PorsmoError::UnknownLanguage(
+                lang.to_string(),
+                known.join(", "),
+            ));
+        };
+        let mut messages = HashMap::new();
+        for table in [parse(CATALOGS[0].1), parse(source)] {
+            for (key, value) in table {
+                if let toml::Value::String(message) = value {
+                    messages.insert(key, message);
+                }
+            }
+        }
+        Ok(Self { messages })
+    }
+
+    /// The catalog for the language named by `LC_ALL`, `LC_MESSAGES` or
+    /// `LANG`, English when none of them names a known one
+    pub fn detect() -> Self {
+        let lang = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        // `de_AT.UTF-8` and `de@euro` both name German
+        let lang = lang.split(['_', '.', '@']).next().unwrap_or_default();
+        Self::new(lang).unwrap_or_else(|_| Self::new("en").expect("English is built in"))
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.messages.get(key).map(String::as_str)
+    }
+}
+
+/// The message for `key` in the configured language, the key itself if
+/// there is no such message
+pub fn tr(key: &'static str) -> &'static str {
+    settings().catalog.get(key).unwrap_or(key)
+}
+
+/// [`tr`] with each `{name}` replaced by its value from `args`
+pub fn tr_with(key: &'static str, args: &[(&str, &dyn Display)]) -> String {
+    let mut message = tr(key).to_string();
+    for (name, value) in args {
+        message = message.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    message
+}
+
+/// Prints `message` to stderr as a warning
+pub fn warn(message: &dyn Display) {
+    eprintln!("{}", tr_with("warning", &[("message", message)]));
+}
+
+/// `1 minute` or `5 minutes`, `unit` is seconds, minutes, hours, pomodoros
+/// or breaks
+pub fn count(n: u64, unit: &str) -> String {
+    let key = format!("{unit}-{}", if n == 1 { "one" } else { "other" });
+    let message = settings().catalog.get(&key).unwrap_or(&key);
+    message.replace("{n}", &n.to_string())
+}
+
+/// Short name of a weekday, 0 is Monday
+pub fn weekday(day: u32) -> &'static str {
+    const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+    tr(DAYS[day as usize % 7])
+}
+
+/// Short name of a month, 0 is January
+pub fn month(month: u32) -> &'static str {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    tr(MONTHS[month as usize % 12])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    /// The `{name}` placeholders in `message`
+    fn placeholders(message: &str) -> BTreeSet<&str> {
+        message
+            .split('{')
+            .skip(1)
+            .filter_map(|part| part.split_once('}').map(|(name, _)| name))
+            .collect()
+    }
+
+    #[test]
+    fn every_catalog_has_the_english_keys_and_placeholders() {
+        let english = parse(CATALOGS[0].1);
+        for (lang, source) in &CATALOGS[1..] {
+            let catalog = parse(source);
+            let keys: BTreeSet<&String> = catalog.keys().collect();
+            assert_eq!(keys, english.keys().collect(), "{lang}");
+            for (key, message) in &english {
+                let message = message.as_str().unwrap();
+                let translated = catalog[key].as_str().unwrap();
+                assert_eq!(
+                    placeholders(translated),
+                    placeholders(message),
+                    "{lang}: {key}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_languages_name_the_known_ones() {
+        assert!(Catalog::new("de").is_ok());
+        assert!(matches!(
+            Catalog::new("xx"),
+            Err(PorsmoError::UnknownLanguage(lang, known)) if lang == "xx" && known == "en, de, fr"
+        ));
+        let german = Catalog::new("de").unwrap();
+        assert_eq!(german.get("pomodoros-one"), Some("1 Pomodoro"));
+        assert_eq!(german.get("no-such-key"), None);
+    }
+}
@@ -1,78 +1,305 @@
 mod alert;
+mod audio;
+mod bigtext;
 mod cli;
+mod config;
 mod error;
+mod export;
 mod format;
+mod heatmap;
+mod history;
+mod hooks;
+mod ical;
 mod input;
+mod layout;
+mod locale;
 mod pomodoro;
 mod prelude;
+mod preset;
+mod stats;
 mod stopwatch;
 mod terminal;
+mod theme;
 mod timer;
 
-use crate::input::{get_event, Command, TIMEOUT};
+use crate::config::{Config, DefaultMode};
+use crate::format::{compact_duration, parse_timer_target, TimerTarget};
+use crate::history::{History, Recorder};
+use crate::input::{get_event, tick, Command};
+use crate::layout::PauseCue;
+use crate::locale::{tr, tr_with, warn};
 use crate::pomodoro::PomodoroConfig;
+use crate::theme::Borders;
 use clap::Parser;
 use cli::{Cli, CounterMode, PomoMode};
 use pomodoro::PomodoroUI;
 use prelude::*;
 use std::io::Write;
+use std::time::Duration;
 use stopwatch::StopwatchUI;
 use terminal::TerminalHandler;
 use timer::TimerUI;
 
-fn main() -> Result<()> {
+fn main() {
+    if let Err(error) = run() {
+        let mut message = error.to_string();
+        let mut source = std::error::Error::source(&error);
+        while let Some(cause) = source {
+            message += &format!(": {cause}");
+            source = cause.source();
+        }
+        eprintln!("{}", tr_with("error", &[("message", &message)]));
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
     let args = Cli::parse();
-    let mut terminal = TerminalHandler::new()?;
-    let stdout = terminal.stdout();
-    let exitmessagestring = match args.mode {
-        Some(CounterMode::Stopwatch) => StopwatchUI::default().run_ui(stdout)?,
-        Some(CounterMode::Timer { target }) => TimerUI::new(target).run_ui(stdout)?,
-        Some(CounterMode::Pomodoro {
-            mode: PomoMode::Short,
-            exitmessage: _,
-        }) => PomodoroUI::new(PomodoroConfig::short()).run_ui(stdout)?,
-        Some(CounterMode::Pomodoro {
-            mode: PomoMode::Long,
-            exitmessage: _,
-        }) => PomodoroUI::new(PomodoroConfig::long()).run_ui(stdout)?,
-        Some(CounterMode::Pomodoro {
-            mode:
+    let mut config = Config::load_or_default(args.config.as_deref())?;
+    if args.no_history {
+        config.history = Some(false);
+    }
+    if args.big {
+        config.ui.big_clock = Some(true);
+    }
+    if let Some(style) = &args.time_style {
+        config.ui.time_style = Some(style.to_string());
+    }
+    if let Some(theme) = &args.theme {
+        config.ui.theme = Some(theme.clone());
+    }
+    if let Some(lang) = &args.lang {
+        config.lang = Some(lang.clone());
+    }
+    if args.colorblind {
+        config.ui.theme = Some("colorblind".into());
+        config.ui.pause_cues = Some(vec![PauseCue::Icon, PauseCue::Label]);
+    }
+    if args.ascii {
+        config.ui.borders = Some(Borders::Ascii);
+    }
+    let mode = args.mode.unwrap_or_else(|| default_mode(&config));
+    // only the counters that alert play sounds
+    let unplayable = match &mode {
+        CounterMode::Timer { .. } => alert::check_sounds(&mut config.alert.sounds),
+        CounterMode::Pomodoro { mode, .. } if !matches!(mode, PomoMode::List) => {
+            alert::check_sounds(&mut config.alert.sounds)
+        }
+        _ => Vec::new(),
+    };
+    config::install(config.settings()?);
+    for (path, error) in &unplayable {
+        warn(&alert::fallback_warning(path, error));
+    }
+    // hooks only run for the pomodoro
+    let pomodoro = matches!(mode, CounterMode::Pomodoro { .. });
+    if let Some(path) = config.untrusted_hooks.as_ref().filter(|_| pomodoro) {
+        warn(&tr_with("untrusted-hooks", &[("file", &path.display())]));
+    }
+
+    let history = if config.history == Some(false) {
+        History::disabled()
+    } else {
+        History::open_default()
+    };
+    // resolve the counter before taking over the terminal so errors stay readable
+    let mut show_exit_message = false;
+    let counter = match mode {
+        CounterMode::Config { print } => {
+            if print {
+                print!(
+                    "{}",
+                    toml::to_string(&config.effective()).map_err(PorsmoError::ConfigPrint)?
+                );
+            } else {
+                match args.config.or_else(Config::default_path) {
+                    Some(path) => println!("{}", path.display()),
+                    None => println!("{}", tr("no-config-dir")),
+                }
+                if let Some(path) = &config.project_file {
+                    println!("{}", path.display());
+                }
+            }
+            return Ok(());
+        }
+        CounterMode::Stats {
+            since,
+            until,
+            by,
+            heatmap,
+        } => {
+            return stats::run_stats(&mut std::io::stdout(), &history, by, since, until, heatmap);
+        }
+        CounterMode::Export {
+            format,
+            mode,
+            since,
+            until,
+            output,
+            include_breaks,
+            merge,
+        } => {
+            let ics = export::IcsOptions {
+                include_breaks,
+                merge,
+            };
+            return export::run_export(
+                &history,
+                format,
+                &mode,
+                since,
+                until,
+                output.as_deref(),
+                ics,
+            );
+        }
+        CounterMode::Stopwatch => Counter::Stopwatch,
+        CounterMode::Timer { target } => Counter::Timer(parse_timer_target(&target.join(" "))?),
+        CounterMode::Pomodoro {
+            mode,
+            exitmessage,
+            work,
+            break_time,
+            long_break,
+            rounds,
+            stop_after,
+        } => {
+            let overridden = work.is_some() || break_time.is_some() || long_break.is_some();
+            if overridden && matches!(mode, PomoMode::Phases { .. }) {
+                return Err(PorsmoError::DurationsWithPhases);
+            }
+            let mut preset_rounds = None;
+            let pomodoro = match mode {
+                PomoMode::Short => config.pomodoro(),
+                PomoMode::Long => PomodoroConfig::long(),
                 PomoMode::Custom {
                     work_time,
                     break_time,
                     long_break,
-                },
-            exitmessage: _,
-        }) => PomodoroUI::new(PomodoroConfig::new(work_time, break_time, long_break))
-            .run_ui(stdout)?,
-        None => PomodoroUI::new(PomodoroConfig::short()).run_ui(stdout)?,
+                } => PomodoroConfig::new(work_time, break_time, long_break),
+                PomoMode::Phases { phases } if phases.is_empty() => {
+                    let phases = config.phase_sequence()?;
+                    if phases.is_empty() {
+                        return Err(PorsmoError::NoPhases);
+                    }
+                    PomodoroConfig::default().with_phases(phases)
+                }
+                // parsed only now since phase titles are translated
+                PomoMode::Phases { phases } => PomodoroConfig::default().with_phases(
+                    phases
+                        .iter()
+                        .map(|phase| phase.parse())
+                        .collect::<Result<_>>()?,
+                ),
+                PomoMode::Use { name } => {
+                    let preset = config.preset(&name)?;
+                    preset_rounds = preset.rounds;
+                    preset.pomodoro()
+                }
+                PomoMode::List => {
+                    return preset::list_presets(&mut std::io::stdout(), &config.presets());
+                }
+            };
+            let pomodoro = pomodoro.with_durations(work, break_time, long_break);
+            pomodoro.validate()?;
+            let stop_after = stop_after.or(config.pomodoro.stop_after);
+            let rounds = rounds
+                .or(preset_rounds)
+                .or(config.pomodoro.rounds)
+                .unwrap_or(pomodoro.long_break_interval);
+            // a pomodoro that stops by itself always ends with its summary
+            show_exit_message = exitmessage
+                || config.pomodoro.exit_message.unwrap_or(false)
+                || stop_after.is_some();
+            Counter::Pomodoro(pomodoro.with_rounds(rounds, stop_after))
+        }
+    };
+
+    let mut tags = args.tags;
+    tags.extend(config.project.tags.iter().cloned());
+    let recorder = Recorder::new(history)
+        .labelled(args.task, tags)
+        .in_project(config.project_name());
+    audio::install(audio::Audio::start(config::settings().audio));
+    let mut terminal = TerminalHandler::new()?;
+    let stdout = terminal.stdout();
+    let exitmessagestring = match counter {
+        Counter::Stopwatch => StopwatchUI::new(recorder).run_ui(stdout)?,
+        Counter::Pomodoro(pomodoro) => PomodoroUI::new(pomodoro, recorder).run_ui(stdout)?,
+        Counter::Timer(target) => TimerUI::new(target, recorder).run_ui(stdout)?,
     };
     drop(terminal);
-    if matches!(
-        args.mode,
-        Some(CounterMode::Pomodoro {
-            mode: _,
-            exitmessage: true
-        })
-    ) {
+    if show_exit_message {
         println!("{}", exitmessagestring);
     }
+    if let Some(error) = history::write_error() {
+        warn(&error);
+    }
+    if let Some(error) = hooks::error() {
+        warn(&error);
+    }
+    alert::finish();
     Ok(())
 }
 
+/// A counter that runs in the terminal, resolved from the command line
+enum Counter {
+    Stopwatch,
+    Pomodoro(PomodoroConfig),
+    Timer(TimerTarget),
+}
+
+/// The subcommand to run when none is given on the command line
+fn default_mode(config: &Config) -> CounterMode {
+    let pomodoro = |mode| CounterMode::Pomodoro {
+        mode,
+        exitmessage: false,
+        work: None,
+        break_time: None,
+        long_break: None,
+        rounds: None,
+        stop_after: None,
+    };
+    match config.default_mode.unwrap_or_default() {
+        DefaultMode::Short => pomodoro(PomoMode::Short),
+        DefaultMode::Long => pomodoro(PomoMode::Long),
+        DefaultMode::Phases => pomodoro(PomoMode::Phases { phases: Vec::new() }),
+        DefaultMode::Timer => CounterMode::Timer {
+            target: vec![compact_duration(
+                config.effective().timer.target.unwrap_or_default(),
+            )],
+        },
+        DefaultMode::Stopwatch => CounterMode::Stopwatch,
+    }
+}
+
 pub trait CounterUI: Sized {
     fn show(&mut self, out: &mut impl Write) -> Result<()>;
-    fn update(&mut self, command: Command);
+    fn update(&mut self, command: Command) -> Result<()>;
+    /// Whether the counter has finished on its own
+    fn done(&self) -> bool {
+        false
+    }
+    /// Called once the user quits, returns the exit message
+    fn quit(self) -> Result<String> {
+        Ok(String::new())
+    }
+    /// How long to wait for input between redraws
+    fn tick(&self) -> Duration {
+        tick()
+    }
     fn run_ui(mut self, out: &mut impl Write) -> Result<String> {
-        loop {
+        while !self.done() {
             self.show(out)?;
-            if let Some(cmd) = get_event(TIMEOUT)?.map(Command::from) {
-                match cmd {
-                    Command::Quit => break,
-                    cmd => self.update(cmd),
-                }
+            match get_event(self.tick())?.map_or(Command::Tick, Command::from) {
+                Command::Quit => break,
+                // the next show lays the frame out for the new size
+                Command::Resize => (),
+                Command::VolumeUp => alert::change_volume(true),
+                Command::VolumeDown => alert::change_volume(false),
+                cmd => self.update(cmd)?,
             }
         }
-        Ok(String::new())
+        self.quit()
     }
 }
@@ -1,75 +1,211 @@
-use crate::alert::Alerter;
-use crate::input::{get_event, TIMEOUT};
+use crate::alert::{alert, AlertKind, Alerter};
+use crate::config::settings;
+use crate::history::{RecordMode, Recorder};
+use crate::hooks::{self, Hook};
+use crate::layout::View;
+use crate::locale::{tr, tr_with};
 use crate::stopwatch::Stopwatch;
-use crate::terminal::running_color;
-use crate::{format::format_duration, input::Command};
+use crate::terminal::{parse_color, running_color};
+use crate::{
+    format::{compact_duration, display_duration, parse_duration},
+    input::{hints, Command},
+};
 use crate::{prelude::*, CounterUI};
-use crossterm::cursor::{MoveTo, MoveToNextLine};
-use crossterm::style::Print;
-use crossterm::terminal::{Clear, ClearType};
-use crossterm::{queue, style::Color, style::Stylize};
+use crossterm::style::Color;
 
+use std::fmt::Display;
 use std::io::Write;
+use std::str::FromStr;
 use std::time::{Duration, Instant};
 
-fn progress_bar(elapsed: Duration, target: Duration, width: usize) -> String {
-    let ratio = if target.is_zero() {
-        1.0
-    } else {
-        (elapsed.as_secs_f64() / target.as_secs_f64()).clamp(0.0, 1.0)
-    };
-    let filled = (ratio * width as f64).round() as usize;
-    let filled = filled.min(width);
-    let empty = width - filled;
-    let percent = (ratio * 100.0).round() as usize;
-    format!("[{}{}] {percent:>3}%", "█".repeat(filled), "-".repeat(empty))
+/// What a phase counts as in the history, stats and summary
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Work,
+    Break,
+    LongBreak,
+    /// Neither focus nor rest, such as planning or a review, never counted
+    /// as a pomodoro
+    Other,
 }
 
-const UI_WIDTH: usize = 50;
+impl FromStr for Mode {
+    type Err = PorsmoError;
 
-fn frame_top() -> String {
-    format!("╭{}╮", "─".repeat(UI_WIDTH))
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "work" => Ok(Self::Work),
+            "break" => Ok(Self::Break),
+            "long-break" | "long_break" => Ok(Self::LongBreak),
+            "other" => Ok(Self::Other),
+            _ => Err(PorsmoError::UnknownPhaseKind(s.to_string())),
+        }
+    }
 }
 
-fn frame_bottom() -> String {
-    format!("╰{}╯", "─".repeat(UI_WIDTH))
+/// A single step of the pomodoro cycle
+#[derive(Clone, Debug)]
+pub struct Phase {
+    pub name: String,
+    pub kind: Mode,
+    pub duration: Duration,
+    /// Shown above the clock while the phase runs
+    pub title: String,
+    /// Color of the title, the kind's color if unset
+    pub color: Option<Color>,
+    /// Shown once the previous phase ended, asking to start this one
+    pub prompt: String,
+    /// Notification sent when it's time to start this phase
+    pub alert_title: String,
+    pub alert_message: String,
 }
 
-fn frame_sep() -> String {
-    format!("│{}│", "─".repeat(UI_WIDTH))
+impl Phase {
+    pub fn work(duration: Duration) -> Self {
+        Self {
+            name: "work".into(),
+            kind: Mode::Work,
+            duration,
+            title: default_title(Mode::Work).into(),
+            color: None,
+            prompt: tr("work-prompt").into(),
+            alert_title: tr("work-alert-title").into(),
+            alert_message: tr("work-alert-message").into(),
+        }
+    }
+
+    pub fn short_break(duration: Duration) -> Self {
+        Self {
+            name: "break".into(),
+            kind: Mode::Break,
+            duration,
+            title: default_title(Mode::Break).into(),
+            color: None,
+            prompt: tr("break-prompt").into(),
+            alert_title: tr("break-alert-title").into(),
+            alert_message: tr("break-alert-message").into(),
+        }
+    }
+
+    pub fn long_break(duration: Duration, after_rounds: u32) -> Self {
+        Self {
+            name: "long-break".into(),
+            kind: Mode::LongBreak,
+            duration,
+            title: default_title(Mode::LongBreak).into(),
+            color: None,
+            prompt: tr("long-break-prompt").into(),
+            alert_title: tr_with("long-break-alert-title", &[("rounds", &after_rounds)]),
+            alert_message: tr("long-break-alert-message").into(),
+        }
+    }
+
+    /// A phase with texts derived from its name, e.g. "review" gets the
+    /// title "Pomodoro (Review)"
+    pub fn named(name: &str, kind: Mode, duration: Duration) -> Self {
+        let label = builtin_label(name).unwrap_or(name);
+        let mut chars = label.chars();
+        let capitalized: String = chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default();
+        Self {
+            name: name.to_string(),
+            kind,
+            duration,
+            title: tr_with("phase-title", &[("name", &capitalized)]),
+            color: None,
+            prompt: tr_with("phase-prompt", &[("name", &label)]),
+            alert_title: tr_with("phase-alert-title", &[("name", &label)]),
+            alert_message: tr_with("phase-alert-message", &[("name", &capitalized)]),
+        }
+    }
 }
 
-fn frame_line(s: &str) -> String {
-    let content = if s.len() >= UI_WIDTH {
-        s[..UI_WIDTH].to_string()
-    } else {
-        format!("{}{}", s, " ".repeat(UI_WIDTH - s.len()))
-    };
-    format!("│{}│", content)
+/// The translated name of the built-in work, break and long-break phases
+fn builtin_label(name: &str) -> Option<&'static str> {
+    match name {
+        "work" => Some(tr("work-name")),
+        "break" => Some(tr("break-name")),
+        "long-break" => Some(tr("long-break-name")),
+        _ => None,
+    }
 }
 
-fn center_text(s: &str) -> String {
-    if s.len() >= UI_WIDTH {
-        s[..UI_WIDTH].to_string()
-    } else {
-        let pad = (UI_WIDTH - s.len()) / 2;
-        format!("{}{}{}", " ".repeat(pad), s, " ".repeat(UI_WIDTH - pad - s.len()))
+impl Phase {
+    /// The name shown to the user, translated for the built-in phases
+    fn label(&self) -> &str {
+        builtin_label(&self.name).unwrap_or(&self.name)
+    }
+
+    /// The configured color, or the theme's title color while running
+    fn running_color(&self) -> Color {
+        self.color.unwrap_or(settings().theme.title)
+    }
+
+    /// The configured color, or the theme's prompt color for the kind when
+    /// asking to skip to this phase
+    fn prompt_color(&self) -> Color {
+        let theme = &settings().theme;
+        self.color.unwrap_or(match self.kind {
+            Mode::Work => theme.work_prompt,
+            Mode::Break | Mode::LongBreak => theme.break_prompt,
+            Mode::Other => theme.other_prompt,
+        })
     }
 }
 
-#[derive(Clone, Copy, Debug, Default)]
-pub enum Mode {
-    #[default]
-    Work,
-    Break,
-    LongBreak,
+impl FromStr for Phase {
+    type Err = PorsmoError;
+
+    /// `name:duration` followed by optional `,key=value` pairs, where key
+    /// is one of kind, title, color, prompt, alert or message. Phases not
+    /// named work, break or long-break are of kind other unless given one
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = |reason| PorsmoError::InvalidPhase(s.to_string(), reason);
+        let mut parts = s.split(',');
+        let head = parts.next().unwrap_or_default();
+        let (name, duration) = head
+            .split_once(':')
+            .ok_or_else(|| invalid("phase-expected-duration"))?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(invalid("phase-missing-name"));
+        }
+        let duration = parse_duration(duration.trim())?;
+        let kind = name.parse().unwrap_or(Mode::Other);
+        let mut phase = Phase::named(name, kind, duration);
+        for part in parts {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| invalid("phase-expected-key-value"))?;
+            let value = value.trim().to_string();
+            match key.trim() {
+                "kind" => phase.kind = value.parse()?,
+                "title" => phase.title = value,
+                "color" => phase.color = Some(parse_color(&value)?),
+                "prompt" => phase.prompt = value,
+                "alert" => phase.alert_title = value,
+                "message" => phase.alert_message = value,
+                _ => return Err(invalid("phase-unknown-key")),
+            }
+        }
+        Ok(phase)
+    }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct PomodoroConfig {
     pub work_time: Duration,
     pub break_time: Duration,
     pub long_break: Duration,
+    /// Work rounds before a long break
+    pub long_break_interval: u32,
+    /// Stop on its own once this many rounds are done
+    pub total_rounds: Option<u32>,
+    /// Phases replacing the work/break/long break cadence above
+    pub phases: Option<Vec<Phase>>,
 }
 
 impl Default for PomodoroConfig {
@@ -84,37 +220,113 @@ impl PomodoroConfig {
             work_time,
             break_time,
             long_break,
+            long_break_interval: 4,
+            total_rounds: None,
+            phases: None,
         }
     }
 
     pub fn short() -> Self {
+        Self::new(
+            Duration::from_secs(25 * 60),
+            Duration::from_secs(5 * 60),
+            Duration::from_secs(10 * 60),
+        )
+    }
+
+    pub fn long() -> Self {
+        Self::new(
+            Duration::from_secs(55 * 60),
+            Duration::from_secs(10 * 60),
+            Duration::from_secs(20 * 60),
+        )
+    }
+
+    pub fn with_rounds(self, long_break_interval: u32, total_rounds: Option<u32>) -> Self {
         Self {
-            work_time: Duration::from_secs(25 * 60),
-            break_time: Duration::from_secs(5 * 60),
-            long_break: Duration::from_secs(10 * 60),
+            long_break_interval: long_break_interval.max(1),
+            total_rounds,
+            ..self
         }
     }
 
-    pub fn long() -> Self {
+    /// Replaces the durations that are given, keeping the others
+    pub fn with_durations(
+        self,
+        work_time: Option<Duration>,
+        break_time: Option<Duration>,
+        long_break: Option<Duration>,
+    ) -> Self {
         Self {
-            work_time: Duration::from_secs(55 * 60),
-            break_time: Duration::from_secs(10 * 60),
-            long_break: Duration::from_secs(20 * 60),
+            work_time: work_time.unwrap_or(self.work_time),
+            break_time: break_time.unwrap_or(self.break_time),
+            long_break: long_break.unwrap_or(self.long_break),
+            ..self
         }
     }
 
-    pub fn current_target(&self, mode: Mode) -> Duration {
-        match mode {
-            Mode::Work => self.work_time,
-            Mode::Break => self.break_time,
-            Mode::LongBreak => self.long_break,
+    /// Rejects durations that would make the pomodoro unusable
+    pub fn validate(&self) -> Result<()> {
+        if self.work_time.is_zero() {
+            return Err(PorsmoError::ZeroDuration("work-name"));
+        }
+        if self.break_time.is_zero() {
+            return Err(PorsmoError::ZeroDuration("break-name"));
+        }
+        if self.long_break < self.break_time {
+            return Err(PorsmoError::LongBreakTooShort(
+                compact_duration(self.long_break),
+                compact_duration(self.break_time),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn with_phases(self, phases: Vec<Phase>) -> Self {
+        Self {
+            phases: Some(phases),
+            ..self
+        }
+    }
+
+    /// The phases one full cycle walks through, in order
+    pub fn sequence(&self) -> Vec<Phase> {
+        match &self.phases {
+            Some(phases) if !phases.is_empty() => phases.clone(),
+            _ => {
+                let mut phases = Vec::new();
+                for round in 1..=self.long_break_interval {
+                    phases.push(Phase::work(self.work_time));
+                    if round == self.long_break_interval {
+                        phases.push(Phase::long_break(self.long_break, round));
+                    } else {
+                        phases.push(Phase::short_break(self.break_time));
+                    }
+                }
+                phases
+            }
         }
     }
 }
 
+fn is_break(phase: &Phase) -> bool {
+    matches!(phase.kind, Mode::Break | Mode::LongBreak)
+}
+
+/// Rounds in one pass through `phases`: a round ends with each break, or
+/// with the end of the sequence if it doesn't end on a break
+pub fn rounds_per_cycle(phases: &[Phase]) -> u32 {
+    let breaks = phases.iter().filter(|phase| is_break(phase)).count() as u32;
+    match phases.last() {
+        Some(last) if !is_break(last) => breaks + 1,
+        _ => breaks.max(1),
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Session {
-    pub mode: Mode,
+    /// Index of the running phase in the sequence
+    pub phase: usize,
     pub round: u32,
     pub elapsed_time: [Duration; 2],
 }
@@ -122,7 +334,7 @@ pub struct Session {
 impl Default for Session {
     fn default() -> Self {
         Self {
-            mode: Mode::default(),
+            phase: 0,
             round: 1,
             elapsed_time: [Duration::ZERO; 2],
         }
@@ -130,57 +342,90 @@ impl Default for Session {
 }
 
 impl Session {
-    pub fn advance(self, duration: Duration) -> Self {
-        match self.mode {
-            Mode::Work if self.round % 4 == 0 => Self {
-                mode: Mode::LongBreak,
-                elapsed_time: [self.elapsed_time[0] + duration, self.elapsed_time[1]],
-                ..self
-            },
-            Mode::Work => Self {
-                mode: Mode::Break,
-                elapsed_time: [self.elapsed_time[0] + duration, self.elapsed_time[1]],
-                ..self
-            },
-            Mode::Break | Mode::LongBreak => Self {
-                mode: Mode::Work,
-                round: self.round + 1,
-                elapsed_time: [self.elapsed_time[0], self.elapsed_time[1] + duration],
+    pub fn phase<'a>(&self, phases: &'a [Phase]) -> &'a Phase {
+        &phases[self.phase % phases.len()]
+    }
+
+    pub fn advance(self, duration: Duration, phases: &[Phase]) -> Self {
+        let current = self.phase(phases);
+        let next = (self.phase + 1) % phases.len();
+        let new_round = is_break(current) || next == 0;
+        let elapsed_time = match current.kind {
+            Mode::Work => [self.elapsed_time[0] + duration, self.elapsed_time[1]],
+            Mode::Break | Mode::LongBreak => {
+                [self.elapsed_time[0], self.elapsed_time[1] + duration]
+            }
+            Mode::Other => self.elapsed_time,
+        };
+        Self {
+            phase: next,
+            round: if new_round {
+                self.round + 1
+            } else {
+                self.round
             },
+            elapsed_time,
         }
     }
 
-    pub fn next(&self) -> Self {
-        self.advance(Duration::ZERO)
+    /// [`Session::advance`] that ends a limited run with its last work
+    /// phase, the breaks after it aren't taken
+    pub fn complete(self, duration: Duration, phases: &[Phase], total_rounds: Option<u32>) -> Self {
+        let last = self.is_last(phases, total_rounds);
+        let mut session = self.advance(duration, phases);
+        while last && !session.is_finished(total_rounds) {
+            session = session.next(phases);
+        }
+        session
     }
-}
 
-const CONTROLS: &str = "[Q]: quit, [Shift S]: Skip, [Space]: pause/resume, [R]: reset";
-const ENDING_CONTROLS: &str =
-    "[Q]: quit, [Shift S]: Skip, [Space]: pause/resume, [Enter]: Next, [R]: reset";
-const SKIP_CONTROLS: &str = "[Enter]: Yes, [Q/N]: No";
+    pub fn next(&self, phases: &[Phase]) -> Self {
+        self.advance(Duration::ZERO, phases)
+    }
 
-fn default_title(mode: Mode) -> &'static str {
-    match mode {
-        Mode::Work => "Pomodoro (Work)",
-        Mode::Break => "Pomodoro (Break)",
-        Mode::LongBreak => "Pomodoro (Long Break)",
+    /// Whether the configured number of rounds is done
+    pub fn is_finished(&self, total_rounds: Option<u32>) -> bool {
+        total_rounds.is_some_and(|total| self.round > total)
     }
-}
 
-fn end_title(next_mode: Mode) -> &'static str {
-    match next_mode {
-        Mode::Work => "Break has ended! Start work?",
-        Mode::Break => "Work has ended! Start break?",
-        Mode::LongBreak => "Work has ended! Start a long break",
+    /// Whether this is the last work phase of the last configured round
+    pub fn is_last(&self, phases: &[Phase], total_rounds: Option<u32>) -> bool {
+        if total_rounds != Some(self.round) || self.phase(phases).kind != Mode::Work {
+            return false;
+        }
+        let mut next = self.next(phases);
+        while next.round == self.round {
+            if next.phase(phases).kind == Mode::Work {
+                return false;
+            }
+            next = next.next(phases);
+        }
+        true
+    }
+
+    /// "Session: 3/4", or "Session: 3/4 (7/12)" when the total is limited
+    pub fn counter(&self, phases: &[Phase], total_rounds: Option<u32>) -> String {
+        let per_cycle = rounds_per_cycle(phases);
+        let position = (self.round - 1) % per_cycle + 1;
+        let args: [(&str, &dyn Display); 4] = [
+            ("position", &position),
+            ("per-cycle", &per_cycle),
+            ("round", &self.round),
+            ("total", &total_rounds.unwrap_or_default()),
+        ];
+        match total_rounds {
+            Some(_) => tr_with("session-limited", &args),
+            None => tr_with("session", &args),
+        }
     }
 }
 
-fn alert_message(next_mode: Mode) -> (&'static str, &'static str) {
-    match next_mode {
-        Mode::Work => ("Your break ended!", "Time for some work"),
-        Mode::Break => ("Pomodoro ended!", "Time for a short break"),
-        Mode::LongBreak => ("Pomodoro 4 sessions complete!", "Time for a long break"),
+pub(crate) fn default_title(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Work => tr("work-title"),
+        Mode::Break => tr("break-title"),
+        Mode::LongBreak => tr("long-break-title"),
+        Mode::Other => tr("other-title"),
     }
 }
 
@@ -196,20 +441,31 @@ impl Default for UIMode {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone)]
 pub struct PomodoroUI {
     config: PomodoroConfig,
+    phases: Vec<Phase>,
     session: Session,
     ui_mode: UIMode,
     alerter: Alerter,
+    recorder: Recorder,
 }
 
 impl PomodoroUI {
-    pub fn new(config: PomodoroConfig) -> Self {
-        Self {
+    /// Sets up the run and starts its first phase
+    pub fn new(config: PomodoroConfig, recorder: Recorder) -> Self {
+        let ui = Self {
+            phases: config.sequence(),
             config,
+            recorder,
             ..Default::default()
-        }
+        };
+        let first = ui.session.phase(&ui.phases);
+        hooks::run(
+            Hook::PhaseStart,
+            &phase_env(first, ui.session.round, &ui.recorder),
+        );
+        ui
     }
 }
 
@@ -218,53 +474,143 @@ impl CounterUI for PomodoroUI {
         pomodoro_show(
             out,
             &self.config,
+            &self.phases,
             &self.ui_mode,
             &self.session,
             &mut self.alerter,
         )
     }
 
-    fn update(&mut self, command: Command) {
+    fn update(&mut self, command: Command) -> Result<()> {
+        let kind = match self.session.phase(&self.phases).kind {
+            Mode::Work => AlertKind::WorkEnd,
+            Mode::Break | Mode::LongBreak | Mode::Other => AlertKind::BreakEnd,
+        };
         pomodoro_update(
             command,
-            &self.config,
+            &self.phases,
+            self.config.total_rounds,
             &mut self.alerter,
+            &mut self.recorder,
             &mut self.ui_mode,
             &mut self.session,
-        );
+        )?;
+        // alert while the loop still runs, main waits for it before exiting
+        if self.done() {
+            alert(self.all_complete(), self.summary(), kind);
+        }
+        Ok(())
     }
 
-    fn run_ui(mut self, out: &mut impl Write) -> Result<String> {
-        loop {
-            self.show(out)?;
-            if let Some(cmd) = get_event(TIMEOUT)?.map(Command::from) {
-                match cmd {
-                    Command::Quit => {
-                        self.session = match self.ui_mode {
-                            UIMode::Skip(elapsed) => self.session.advance(elapsed),
-                            UIMode::Running(stopwatch) => self.session.advance(stopwatch.elapsed()),
-                        };
-                        break;
-                    }
-                    cmd => self.update(cmd),
-                }
-            }
+    fn done(&self) -> bool {
+        self.session.is_finished(self.config.total_rounds)
+    }
+
+    fn quit(mut self) -> Result<String> {
+        let finished = self.done();
+        if !finished {
+            let elapsed = match self.ui_mode {
+                UIMode::Skip(elapsed) => elapsed,
+                UIMode::Running(stopwatch) => stopwatch.elapsed(),
+            };
+            finish_phase(
+                &self.phases,
+                self.config.total_rounds,
+                &mut self.recorder,
+                &mut self.session,
+                elapsed,
+                false,
+            );
         }
-        Ok(format!(
-            "You have spent {} working and {} on break. Well done!",
-            format_duration(self.session.elapsed_time[0]),
-            format_duration(self.session.elapsed_time[1]),
-        ))
+        if finished {
+            Ok(format!("{} {}", self.all_complete(), self.summary()))
+        } else {
+            Ok(self.summary())
+        }
+    }
+}
+
+impl PomodoroUI {
+    fn summary(&self) -> String {
+        let summary = tr_with(
+            "summary",
+            &[
+                ("work", &display_duration(self.session.elapsed_time[0])),
+                ("break", &display_duration(self.session.elapsed_time[1])),
+            ],
+        );
+        match self.recorder.project() {
+            Some(project) => format!("[{project}] {summary}"),
+            None => summary,
+        }
+    }
+
+    /// "All 4 sessions complete!" once a limited run is done
+    fn all_complete(&self) -> String {
+        tr_with("all-complete", &[("rounds", &(self.session.round - 1))])
+    }
+}
+
+/// What the phase hooks get told about `phase`
+fn phase_env(phase: &Phase, round: u32, recorder: &Recorder) -> Vec<(&'static str, String)> {
+    vec![
+        ("PHASE", phase.name.clone()),
+        ("KIND", RecordMode::from(phase.kind).name().into()),
+        ("ROUND", round.to_string()),
+        ("PROJECT", recorder.project().unwrap_or_default().into()),
+    ]
+}
+
+/// Records the current phase to the history and moves the session on
+fn finish_phase(
+    phases: &[Phase],
+    total_rounds: Option<u32>,
+    recorder: &mut Recorder,
+    session: &mut Session,
+    elapsed: Duration,
+    skipped: bool,
+) {
+    let phase = session.phase(phases);
+    recorder.record(
+        phase.kind.into(),
+        Some(&phase.name),
+        Some(session.round),
+        Some(phase.duration),
+        elapsed,
+        skipped,
+    );
+    let mut env = phase_env(phase, session.round, recorder);
+    env.push(("ELAPSED", elapsed.as_secs().to_string()));
+    env.push(("SKIPPED", skipped.to_string()));
+    hooks::run(Hook::PhaseEnd, &env);
+    *session = session.complete(elapsed, phases, total_rounds);
+}
+
+/// Moves on to the next phase once the current one is finished or skipped
+fn next_phase(
+    phases: &[Phase],
+    total_rounds: Option<u32>,
+    recorder: &mut Recorder,
+    session: &mut Session,
+    elapsed: Duration,
+    skipped: bool,
+) {
+    finish_phase(phases, total_rounds, recorder, session, elapsed, skipped);
+    if !session.is_finished(total_rounds) {
+        let env = phase_env(session.phase(phases), session.round, recorder);
+        hooks::run(Hook::PhaseStart, &env);
     }
 }
 
 fn pomodoro_update(
     command: Command,
-    config: &PomodoroConfig,
+    phases: &[Phase],
+    total_rounds: Option<u32>,
     alerter: &mut Alerter,
+    recorder: &mut Recorder,
     ui_mode: &mut UIMode,
     session: &mut Session,
-) {
+) -> Result<()> {
     match ui_mode {
         UIMode::Skip(elapsed) => match command {
             Command::Quit | Command::No => {
@@ -272,247 +618,298 @@ fn pomodoro_update(
             }
             Command::Enter | Command::Yes => {
                 alerter.reset();
-                *session = session.advance(*elapsed);
+                next_phase(phases, total_rounds, recorder, session, *elapsed, true);
                 *ui_mode = UIMode::Running(Stopwatch::default());
             }
             _ => (),
         },
         UIMode::Running(ref mut stopwatch) => {
             let elapsed = stopwatch.elapsed();
-            let target = config.current_target(session.mode);
+            let target = session.phase(phases).duration;
 
             match command {
                 Command::Enter if elapsed >= target => {
                     alerter.reset();
-                    *session = session.advance(elapsed);
+                    next_phase(phases, total_rounds, recorder, session, elapsed, false);
                     *ui_mode = UIMode::Running(Stopwatch::default());
                 }
+                // the last phase of a limited run ends by itself
+                Command::Tick if elapsed >= target && session.is_last(phases, total_rounds) => {
+                    finish_phase(phases, total_rounds, recorder, session, elapsed, false);
+                }
                 Command::Pause => stopwatch.stop(),
                 Command::Resume => stopwatch.start(),
                 Command::Toggle => stopwatch.toggle(),
                 Command::Skip => *ui_mode = UIMode::Skip(elapsed),
-                Command::Reset => *stopwatch = Stopwatch::default(),
+                Command::Acknowledge => alerter.acknowledge(),
+                Command::Reset => {
+                    recorder.restart();
+                    *stopwatch = Stopwatch::default();
+                }
                 _ => (),
             }
         }
     }
+    Ok(())
 }
 
 fn pomodoro_show(
     out: &mut impl Write,
     config: &PomodoroConfig,
+    phases: &[Phase],
     ui_mode: &UIMode,
     session: &Session,
     alerter: &mut Alerter,
 ) -> Result<()> {
-    let target = config.current_target(session.mode);
-    let round_number = format!("Session: {}", session.round);
+    let phase = session.phase(phases);
+    let next_phase = session.next(phases).phase(phases);
+    let target = phase.duration;
+    let round_number = session.counter(phases, config.total_rounds);
     match ui_mode {
-        UIMode::Skip(..) => {
-            let (color, skip_to) = match session.next().mode {
-                Mode::Work => (Color::Red, "skip to work?"),
-                Mode::Break => (Color::Green, "skip to break?"),
-                Mode::LongBreak => (Color::Green, "skip to long break?"),
-            };
-            queue!(
-                out,
-                MoveTo(0, 0),
-                Print(frame_top()),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                // title
-                Print("│"),
-                Print(center_text(skip_to).with(color)),
-                Print("│"),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print(frame_sep()),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                // blank line above where time would be
-                Print(frame_line("")),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                // progress bar (centered)
-                Print("│"),
-                Print(" ".repeat((UI_WIDTH - (1 + 30 + 1 + 1 + 4)) / 2)),
-                Print("["),
-                Print("-".repeat(30).with(Color::DarkGrey)),
-                Print("] "),
-                Print("  0%".with(Color::White)),
-                Print(" ".repeat((UI_WIDTH - (1 + 30 + 1 + 1 + 4) + 1) / 2)),
-                Print("│"),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                // blank line below progress for symmetry
-                Print(frame_line("")),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print(frame_sep()),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                // controls (uncolored borders, dimmed text)
-                Print("│"),
-                Print(SKIP_CONTROLS.with(Color::DarkGrey)),
-                Print(" ".repeat(UI_WIDTH.saturating_sub(SKIP_CONTROLS.len()))),
-                Print("│"),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print(frame_line(&round_number)),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print(frame_bottom()),
-                Clear(ClearType::FromCursorDown),
-            )?;
+        UIMode::Skip(..) => View {
+            title: &tr_with("skip-to", &[("phase", &next_phase.label())]),
+            title_color: Some(next_phase.prompt_color()),
+            progress: Some((0.0, settings().theme.bar)),
+            controls: hints(&[
+                (&[Command::Enter], "action-yes"),
+                (&[Command::Quit, Command::No], "action-no"),
+            ]),
+            footer: vec![round_number],
+            ..Default::default()
         }
+        .draw(out),
         UIMode::Running(stopwatch) if stopwatch.elapsed() < target => {
             let time_left = target.saturating_sub(stopwatch.elapsed());
-            let time_raw = format_duration(&time_left);
-            let styled_time = time_raw.clone().with(running_color(stopwatch.started()));
-            let bar_width = 30usize;
+            alerter.warn_once(&phase.title, time_left, target);
+            let time_raw = settings().time_style.countdown(time_left);
             let ratio = if target.is_zero() {
                 1.0
             } else {
-                (stopwatch.elapsed().as_secs_f64() / target.as_secs_f64()).clamp(0.0, 1.0)
+                stopwatch.elapsed().as_secs_f64() / target.as_secs_f64()
             };
-            let filled = (ratio * bar_width as f64).round() as usize;
-            let empty = bar_width.saturating_sub(filled);
-            let percent = (ratio * 100.0).round() as usize;
-            let percent_str = format!("{percent:>3}%");
-            let content_len = 1 + bar_width + 1 + 1 + percent_str.len();
-            let pad_left = (UI_WIDTH.saturating_sub(content_len)) / 2;
-            let pad_right = UI_WIDTH.saturating_sub(content_len + pad_left);
-
-            // split controls into two reasonable lines to avoid truncation
-            let parts: Vec<&str> = CONTROLS.split(',').map(|s| s.trim()).collect();
-            let mid = (parts.len() + 1) / 2;
-            let controls1 = parts[..mid].join(", ");
-            let controls2 = parts[mid..].join(", ");
-            let controls1_len = controls1.len();
-            let controls2_len = controls2.len();
-
-            queue!(
-                out,
-                MoveTo(0, 0),
-                Print(frame_top()),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                // title: print borders separately so frame isn't colored
-                Print("│"),
-                Print(center_text(default_title(session.mode)).with(Color::Cyan)),
-                Print("│"),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print(frame_sep()),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                // blank line above time for symmetry
-                Print(frame_line("")),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                // time line (centered)
-                Print("│"),
-                Print(" ".repeat((UI_WIDTH.saturating_sub(time_raw.len()))/2)),
-                Print(styled_time),
-                Print(" ".repeat(UI_WIDTH.saturating_sub(time_raw.len()) - (UI_WIDTH.saturating_sub(time_raw.len()))/2)),
-                Print("│"),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                // blank line below time for symmetry
-                Print(frame_line("")),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                // centered colored progress bar
-                Print("│"),
-                Print(" ".repeat(pad_left)),
-                Print("["),
-                Print("█".repeat(filled).with(running_color(stopwatch.started()))),
-                Print("-".repeat(empty).with(Color::DarkGrey)),
-                Print("] "),
-                Print(percent_str.with(Color::White)),
-                Print(" ".repeat(pad_right)),
-                Print("│"),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                // middle separator (blank line for spacing)
-                Print(frame_line("")),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print(frame_sep()),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                // controls split into two lines and dimmed (print borders separately)
-                Print("│"),
-                Print(controls1.clone().with(Color::DarkGrey)),
-                Print(" ".repeat(UI_WIDTH.saturating_sub(controls1_len))),
-                Print("│"),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print("│"),
-                Print(controls2.clone().with(Color::DarkGrey)),
-                Print(" ".repeat(UI_WIDTH.saturating_sub(controls2_len))),
-                Print("│"),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print(frame_line(&round_number)),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print(frame_bottom()),
-                Clear(ClearType::FromCursorDown),
-            )?;
+            let color = running_color(stopwatch.started());
+            View {
+                title: &phase.title,
+                title_color: Some(phase.running_color()),
+                clock: Some((time_raw, color)),
+                paused: !stopwatch.started(),
+                progress: Some((ratio, color)),
+                controls: hints(&[
+                    (&[Command::Quit], "action-quit"),
+                    (&[Command::Skip], "action-skip"),
+                    (&[Command::Toggle], "action-pause"),
+                    (&[Command::Reset], "action-reset"),
+                    (&[Command::VolumeUp, Command::VolumeDown], "action-volume"),
+                ]),
+                footer: vec![round_number],
+                ..Default::default()
+            }
+            .draw(out)
         }
         UIMode::Running(stopwatch) => {
             let excess_time = stopwatch.elapsed().saturating_sub(target);
-            let (title, message) = alert_message(session.next().mode);
-            alerter.alert_once(title, message);
-
-            let plus_raw = format!("+{}", format_duration(&excess_time));
-            let pad_plus = UI_WIDTH.saturating_sub(plus_raw.len());
-
-            queue!(
-                out,
-                MoveTo(0, 0),
-                Print(frame_top()),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print(frame_line(&center_text(end_title(session.next().mode)))),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print(frame_sep()),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                // blank line above excess time for symmetry
-                Print(frame_line("")),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                // excess time line with styling but correct padding
-                Print("│"),
-                Print(plus_raw.with(running_color(stopwatch.started()))),
-                Print(" ".repeat(pad_plus)),
-                Print("│"),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                // blank line below excess time for symmetry
-                Print(frame_line("")),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print(frame_line(&progress_bar(target, target, 30))),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print(frame_line(ENDING_CONTROLS)),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print(frame_line(&round_number)),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print(frame_line(message)),
-                Clear(ClearType::UntilNewLine),
-                MoveToNextLine(1),
-                Print(frame_bottom()),
-                Clear(ClearType::FromCursorDown),
-            )?;
-        }
-    }
-    out.flush()?;
-    Ok(())
+            let message = &next_phase.alert_message;
+            // the last phase alerts that the run is complete once it ends
+            if !session.is_last(phases, config.total_rounds) {
+                alerter.alarm(
+                    &next_phase.alert_title,
+                    message,
+                    AlertKind::ending(phase.kind, next_phase.kind),
+                );
+            }
+
+            let plus_raw = format!("+{}", display_duration(excess_time));
+            let color = running_color(stopwatch.started());
+            View {
+                title: &next_phase.prompt,
+                clock: Some((plus_raw, color)),
+                paused: !stopwatch.started(),
+                progress: Some((1.0, color)),
+                controls: hints(&[
+                    (&[Command::Quit], "action-quit"),
+                    (&[Command::Skip], "action-skip"),
+                    (&[Command::Toggle], "action-pause"),
+                    (&[Command::Enter], "action-next"),
+                    (&[Command::Reset], "action-reset"),
+                    (&[Command::VolumeUp, Command::VolumeDown], "action-volume"),
+                    (&[Command::Acknowledge], "action-silence"),
+                ]),
+                footer: vec![round_number, message.clone()],
+                ..Default::default()
+            }
+            .draw(out)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn skipping_the_last_work_phase_finishes_the_run() {
+        let config = PomodoroConfig::short().with_rounds(4, Some(2));
+        let phases = config.sequence();
+        let mut session = Session::default();
+        // work and break of the first round, then the last work phase is
+        // skipped a minute in
+        session = session.complete(25 * MINUTE, &phases, config.total_rounds);
+        session = session.complete(5 * MINUTE, &phases, config.total_rounds);
+        assert_eq!(session.round, 2);
+        assert!(session.is_last(&phases, config.total_rounds));
+        session = session.complete(MINUTE, &phases, config.total_rounds);
+        assert!(session.is_finished(config.total_rounds));
+        assert_eq!(session.round, 3);
+        assert_eq!(session.elapsed_time, [26 * MINUTE, 5 * MINUTE]);
+    }
+
+    #[test]
+    fn custom_cycles_count_rounds_at_breaks() {
+        let config = PomodoroConfig::default()
+            .with_phases(vec![
+                Phase::named("plan", Mode::Other, 5 * MINUTE),
+                Phase::work(25 * MINUTE),
+                Phase::short_break(5 * MINUTE),
+            ])
+            .with_rounds(4, Some(2));
+        let phases = config.sequence();
+        assert_eq!(rounds_per_cycle(&phases), 1);
+        let mut session = Session::default();
+        session = session.complete(5 * MINUTE, &phases, config.total_rounds);
+        assert_eq!((session.phase, session.round), (1, 1));
+        session = session.complete(25 * MINUTE, &phases, config.total_rounds);
+        assert_eq!((session.phase, session.round), (2, 1));
+        session = session.complete(5 * MINUTE, &phases, config.total_rounds);
+        assert_eq!((session.phase, session.round), (0, 2));
+        // planning time is neither work nor break
+        assert_eq!(session.elapsed_time, [25 * MINUTE, 5 * MINUTE]);
+
+        session = session.complete(5 * MINUTE, &phases, config.total_rounds);
+        assert!(session.is_last(&phases, config.total_rounds));
+        session = session.complete(25 * MINUTE, &phases, config.total_rounds);
+        assert!(session.is_finished(config.total_rounds));
+        assert_eq!(session.elapsed_time, [50 * MINUTE, 5 * MINUTE]);
+    }
+
+    #[test]
+    fn only_the_last_work_phase_of_a_round_is_last() {
+        let phases = vec![
+            Phase::work(MINUTE),
+            Phase::work(MINUTE),
+            Phase::long_break(MINUTE, 1),
+        ];
+        let total = Some(1);
+        let session = Session::default();
+        assert!(!session.is_last(&phases, total));
+        let session = session.complete(MINUTE, &phases, total);
+        assert_eq!(session.round, 1);
+        assert!(session.is_last(&phases, total));
+        let session = session.complete(MINUTE, &phases, total);
+        assert!(session.is_finished(total));
+        assert_eq!(session.elapsed_time, [2 * MINUTE, Duration::ZERO]);
+    }
+
+    #[test]
+    fn a_cycle_not_ending_on_a_break_ends_a_round_too() {
+        let phases = vec![
+            Phase::work(MINUTE),
+            Phase::short_break(MINUTE),
+            Phase::named("review", Mode::Other, MINUTE),
+        ];
+        assert_eq!(rounds_per_cycle(&phases), 2);
+        let mut session = Session::default();
+        let mut rounds = vec![session.round];
+        for _ in 0..3 {
+            session = session.advance(MINUTE, &phases);
+            rounds.push(session.round);
+        }
+        // the review is a round of its own after the break
+        assert_eq!(rounds, [1, 1, 2, 3]);
+        assert_eq!(session.phase, 0);
+    }
+
+    #[test]
+    fn phases_parse_with_options() {
+        let phase: Phase = "review:5m,kind=break,title=Look back,color=blue"
+            .parse()
+            .unwrap();
+        assert_eq!(phase.name, "review");
+        assert_eq!(phase.kind, Mode::Break);
+        assert_eq!(phase.duration, 5 * MINUTE);
+        assert_eq!(phase.title, "Look back");
+        assert_eq!(phase.color, Some(Color::Blue));
+
+        let plan: Phase = "plan:90s".parse().unwrap();
+        assert_eq!(plan.kind, Mode::Other);
+        let work: Phase = " work : 25m".parse().unwrap();
+        assert_eq!((work.name.as_str(), work.kind), ("work", Mode::Work));
+
+        for bad in [
+            "plan",
+            ":5m",
+            "plan:5m,loud",
+            "plan:5m,size=2",
+            "plan:5m,kind=nap",
+        ] {
+            assert!(bad.parse::<Phase>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn the_long_break_comes_after_the_configured_rounds() {
+        let kinds = |config: PomodoroConfig| -> Vec<Mode> {
+            config.sequence().iter().map(|phase| phase.kind).collect()
+        };
+        let short = PomodoroConfig::short();
+        assert_eq!(
+            kinds(short.clone().with_rounds(2, None)),
+            [Mode::Work, Mode::Break, Mode::Work, Mode::LongBreak]
+        );
+        assert_eq!(
+            kinds(short.clone().with_rounds(0, None)),
+            [Mode::Work, Mode::LongBreak]
+        );
+        let phases = short.with_rounds(3, None).sequence();
+        assert_eq!(phases.len(), 6);
+        assert_eq!(rounds_per_cycle(&phases), 3);
+    }
+
+    #[test]
+    fn overrides_replace_only_the_durations_given() {
+        let preset = PomodoroConfig::new(52 * MINUTE, 17 * MINUTE, 17 * MINUTE);
+        let config = preset
+            .clone()
+            .with_durations(Some(30 * MINUTE), None, Some(20 * MINUTE));
+        assert_eq!(config.work_time, 30 * MINUTE);
+        assert_eq!(config.break_time, 17 * MINUTE);
+        assert_eq!(config.long_break, 20 * MINUTE);
+        config.validate().unwrap();
+
+        let no_work = preset
+            .clone()
+            .with_durations(Some(Duration::ZERO), None, None);
+        assert!(matches!(
+            no_work.validate(),
+            Err(PorsmoError::ZeroDuration("work-name"))
+        ));
+        let long_break = preset.with_durations(None, Some(20 * MINUTE), None);
+        assert!(matches!(
+            long_break.validate(),
+            Err(PorsmoError::LongBreakTooShort(..))
+        ));
+    }
+
+    #[test]
+    fn unlimited_runs_never_finish() {
+        let config = PomodoroConfig::short();
+        let phases = config.sequence();
+        let mut session = Session::default();
+        for _ in 0..20 {
+            session = session.complete(MINUTE, &phases, None);
+            assert!(!session.is_finished(None));
+        }
+        assert_eq!(session.round, 11);
+    }
 }
@@ -0,0 +1,129 @@
+use crate::format::compact_duration;
+use crate::layout::text_width;
+use crate::pomodoro::PomodoroConfig;
+use crate::prelude::*;
+use std::io::Write;
+use std::time::Duration;
+
+/// A named set of pomodoro durations
+#[derive(Clone, Debug)]
+pub struct Preset {
+    pub name: String,
+    pub description: String,
+    pub work_time: Duration,
+    pub break_time: Duration,
+    pub long_break: Duration,
+    /// Work rounds before a long break, the configured default when unset
+    pub rounds: Option<u32>,
+}
+
+impl Preset {
+    fn builtin(name: &str, description: &str, minutes: [u64; 3]) -> Self {
+        let [work, short, long] = minutes.map(|min| Duration::from_secs(min * 60));
+        Self {
+            name: name.into(),
+            description: description.into(),
+            work_time: work,
+            break_time: short,
+            long_break: long,
+            rounds: None,
+        }
+    }
+
+    pub fn pomodoro(&self) -> PomodoroConfig {
+        PomodoroConfig::new(self.work_time, self.break_time, self.long_break)
+    }
+}
+
+/// Presets that ship with porsmo, `short` and `long` match the subcommands
+pub fn builtin_presets() -> Vec<Preset> {
+    vec![
+        Preset::builtin("short", "the classic pomodoro", [25, 5, 10]),
+        Preset::builtin("long", "longer stretches of focus", [55, 10, 20]),
+        Preset::builtin("52-17", "52 minutes on, 17 off", [52, 17, 17]),
+        Preset::builtin("ultradian", "one 90 minute ultradian cycle", [90, 20, 20]),
+        Preset::builtin("50-10", "50 minutes on, 10 off", [50, 10, 20]),
+        Preset::builtin("animedoro", "break for an episode of anime", [40, 20, 20]),
+    ]
+}
+
+pub fn list_presets(out: &mut impl Write, presets: &[Preset]) -> Result<()> {
+    let width = presets
+        .iter()
+        .map(|preset| text_width(&preset.name))
+        .max()
+        .unwrap_or(0);
+    writeln!(
+        out,
+        "{:width$}  {:>5}  {:>5}  {:>5}  {:>6}  description",
+        "name", "work", "break", "long", "rounds"
+    )?;
+    for preset in presets {
+        let rounds = preset
+            .rounds
+            .map_or_else(|| "-".to_string(), |rounds| rounds.to_string());
+        // `{:width$}` pads by chars, wide characters need more room
+        let name_width = width + preset.name.chars().count() - text_width(&preset.name);
+        writeln!(
+            out,
+            "{:name_width$}  {:>5}  {:>5}  {:>5}  {:>6}  {}",
+            preset.name,
+            compact_duration(preset.work_time),
+            compact_duration(preset.break_time),
+            compact_duration(preset.long_break),
+            rounds,
+            preset.description,
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_presets_are_valid_and_unique() {
+        let presets = builtin_presets();
+        for preset in &presets {
+            preset.pomodoro().validate().unwrap();
+            let same_name = presets.iter().filter(|other| other.name == preset.name);
+            assert_eq!(same_name.count(), 1, "{}", preset.name);
+        }
+        let short = PomodoroConfig::short();
+        assert_eq!(presets[0].work_time, short.work_time);
+    }
+
+    #[test]
+    fn the_list_lines_up_columns() {
+        let mut presets = builtin_presets();
+        presets[1].rounds = Some(2);
+        let mut out = Vec::new();
+        list_presets(&mut out, &presets).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), presets.len() + 1);
+        // every description starts in the column of the header's
+        let header = lines[0].find("description").unwrap();
+        for (line, preset) in lines[1..].iter().zip(&presets) {
+            assert_eq!(line.len() - preset.description.len(), header, "{line}");
+        }
+        assert!(lines[2].starts_with(&format!("long{}55m", " ".repeat(9))));
+        assert!(lines[2].contains("      2  longer"), "{}", lines[2]);
+    }
+
+    #[test]
+    fn wide_names_are_padded_by_columns() {
+        let mut presets = builtin_presets();
+        presets[0].name = "短い".into();
+        let mut out = Vec::new();
+        list_presets(&mut out, &presets).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        let header = lines[0].find("description").unwrap();
+        let start = lines[1].len() - presets[0].description.len();
+        assert_eq!(text_width(&lines[1][..start]), header);
+        assert!(lines[1].starts_with(&format!("短い{}25m", " ".repeat(9))));
+    }
+}
@@ -0,0 +1,266 @@
+use crate::config::settings;
+use crate::format::{format_duration, TimeBound};
+use crate::heatmap::heatmap_show;
+use crate::history::{History, Record, RecordMode};
+use crate::layout::Frame;
+use crate::locale::{count, tr, tr_with};
+use crate::prelude::*;
+use crate::terminal::supports_color;
+use chrono::{DateTime, Datelike, Local};
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::time::Duration;
+
+const STATS_WIDTH: usize = 50;
+
+/// How focus time is grouped in the report
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum Period {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl Period {
+    fn key(&self, time: &DateTime<Local>) -> String {
+        match self {
+            Self::Day => time.format("%Y-%m-%d").to_string(),
+            Self::Week => {
+                let week = time.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            Self::Month => time.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Bucket {
+    focus: Duration,
+    pomodoros: u32,
+}
+
+fn percent(part: usize, total: usize) -> String {
+    if total == 0 {
+        "-".to_string()
+    } else {
+        format!("{}%", (part as f64 / total as f64 * 100.0).round())
+    }
+}
+
+/// `label` left aligned, `value` starting at a fixed column
+fn row(label: &str, value: &str) -> String {
+    format!(" {label:<22}{value}")
+}
+
+pub fn stats_show(
+    out: &mut impl Write,
+    records: &[Record],
+    period: Period,
+    since: Option<TimeBound>,
+    until: Option<TimeBound>,
+) -> Result<()> {
+    let since = since.map(|bound| bound.start());
+    let until = until.map(|bound| bound.end());
+    let records: Vec<&Record> = records
+        .iter()
+        .filter(|record| record.started_within(since, until))
+        .collect();
+
+    let mut buckets: BTreeMap<String, Bucket> = BTreeMap::new();
+    for record in records
+        .iter()
+        .filter(|record| record.mode == RecordMode::Work)
+    {
+        let bucket = buckets.entry(period.key(&record.start)).or_default();
+        bucket.focus += record.elapsed;
+        if record.is_completed_pomodoro() {
+            bucket.pomodoros += 1;
+        }
+    }
+
+    let phases: Vec<&&Record> = records
+        .iter()
+        .filter(|record| {
+            matches!(
+                record.mode,
+                RecordMode::Work | RecordMode::Break | RecordMode::LongBreak
+            )
+        })
+        .collect();
+    let total_focus: Duration = buckets.values().map(|bucket| bucket.focus).sum();
+    let completed: u32 = buckets.values().map(|bucket| bucket.pomodoros).sum();
+    // every phase run to its end counts, the ones ended on time as zero
+    let overtimes: Vec<Duration> = phases
+        .iter()
+        .filter(|record| {
+            !record.skipped && record.target.is_some_and(|target| record.elapsed >= target)
+        })
+        .map(|record| record.overtime)
+        .collect();
+    let average_overtime = match overtimes.len() {
+        0 => Duration::ZERO,
+        n => overtimes.iter().sum::<Duration>() / n as u32,
+    };
+    let work: Vec<_> = phases
+        .iter()
+        .filter(|record| record.mode == RecordMode::Work)
+        .collect();
+    let breaks: Vec<_> = phases
+        .iter()
+        .filter(|record| record.mode != RecordMode::Work)
+        .collect();
+    let skip_rate = tr_with(
+        "stats-skip-rates",
+        &[
+            (
+                "work",
+                &percent(work.iter().filter(|r| r.skipped).count(), work.len()),
+            ),
+            (
+                "breaks",
+                &percent(breaks.iter().filter(|r| r.skipped).count(), breaks.len()),
+            ),
+        ],
+    );
+
+    let mut frame = Frame::new(STATS_WIDTH);
+    frame
+        .center(tr("stats-title"), Some(settings().theme.title))
+        .separator();
+    if buckets.is_empty() {
+        frame.line(&format!(" {}", tr("stats-empty")), None);
+    }
+    for (key, bucket) in &buckets {
+        let line = format!(
+            " {key:<10}  {:>12}  {:>14}",
+            format_duration(bucket.focus),
+            count(bucket.pomodoros.into(), "pomodoros"),
+        );
+        frame.line(&line, None);
+    }
+    frame
+        .separator()
+        .line(
+            &row(tr("stats-total-focus"), &format_duration(total_focus)),
+            None,
+        )
+        .line(&row(tr("stats-completed"), &completed.to_string()), None)
+        .line(
+            &row(
+                tr("stats-average-overtime"),
+                &format_duration(average_overtime),
+            ),
+            None,
+        )
+        .line(&row(tr("stats-skip-rate"), &skip_rate), None);
+    frame.print(out)
+}
+
+pub fn run_stats(
+    out: &mut impl Write,
+    history: &History,
+    period: Period,
+    since: Option<TimeBound>,
+    until: Option<TimeBound>,
+    heatmap: bool,
+) -> Result<()> {
+    let records = history.load()?;
+    if heatmap {
+        heatmap_show(out, &records, supports_color())
+    } else {
+        stats_show(out, &records, period, since, until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn record(mode: RecordMode, start: DateTime<Local>, elapsed: u64, target: u64) -> Record {
+        let elapsed = Duration::from_secs(elapsed);
+        let target = Duration::from_secs(target);
+        Record {
+            mode,
+            phase: None,
+            round: None,
+            start,
+            end: start + chrono::Duration::from_std(elapsed).unwrap(),
+            target: Some(target),
+            elapsed,
+            overtime: elapsed.saturating_sub(target),
+            skipped: false,
+            tags: Vec::new(),
+            task: None,
+            project: None,
+        }
+    }
+
+    fn at(day: u32, hour: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 1, day, hour, 0, 0).unwrap()
+    }
+
+    fn show(records: &[Record], period: Period) -> String {
+        let mut out = Vec::new();
+        stats_show(&mut out, records, period, None, None).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn periods_group_by_iso_week() {
+        // 2023-01-01 is a Sunday, the last day of the 52nd week of 2022
+        let sunday = Local.with_ymd_and_hms(2023, 1, 1, 12, 0, 0).unwrap();
+        assert_eq!(Period::Day.key(&sunday), "2023-01-01");
+        assert_eq!(Period::Week.key(&sunday), "2022-W52");
+        assert_eq!(Period::Month.key(&sunday), "2023-01");
+        assert_eq!(Period::Week.key(&at(1, 12)), "2024-W01");
+    }
+
+    #[test]
+    fn buckets_count_completed_pomodoros_only() {
+        let mut skipped = record(RecordMode::Work, at(1, 11), 600, 1500);
+        skipped.skipped = true;
+        let records = [
+            record(RecordMode::Work, at(1, 9), 1500, 1500),
+            record(RecordMode::Work, at(1, 10), 1620, 1500),
+            skipped,
+            record(RecordMode::Break, at(1, 12), 300, 300),
+            record(RecordMode::Work, at(2, 9), 1500, 1500),
+            record(RecordMode::Timer, at(2, 10), 60, 60),
+        ];
+        let days = show(&records, Period::Day);
+        assert!(days.contains(&format!(
+            "2024-01-01      1h 2m 0s  {:>14}",
+            count(2, "pomodoros")
+        )));
+        assert!(days.contains(&format!(
+            "2024-01-02     0h 25m 0s  {:>14}",
+            count(1, "pomodoros")
+        )));
+        assert!(days.contains(&row(tr("stats-completed"), "3")));
+        let weeks = show(&records, Period::Week);
+        assert!(weeks.contains("2024-W01"));
+        assert!(!weeks.contains("2024-01-01"));
+    }
+
+    #[test]
+    fn average_overtime_counts_phases_ended_on_time_as_zero() {
+        let mut skipped = record(RecordMode::Work, at(1, 11), 1800, 1500);
+        skipped.skipped = true;
+        let records = [
+            record(RecordMode::Work, at(1, 9), 1620, 1500),
+            record(RecordMode::Break, at(1, 10), 300, 300),
+            record(RecordMode::Work, at(1, 10), 1500, 1500),
+            // neither a skipped phase nor one cut short counts
+            skipped,
+            record(RecordMode::Work, at(1, 12), 600, 1500),
+        ];
+        let stats = show(&records, Period::Day);
+        assert!(stats.contains(&row(tr("stats-average-overtime"), "0h 0m 40s")));
+        let skip_rate = tr_with("stats-skip-rates", &[("work", &"25%"), ("breaks", &"0%")]);
+        assert!(stats.contains(&skip_rate));
+    }
+}
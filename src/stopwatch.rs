@@ -1,47 +1,13 @@
 use std::time::Instant;
 use std::{io::Write, time::Duration};
 
-use crate::{prelude::*, CounterUI};
+use crate::config::settings;
+use crate::history::{RecordMode, Recorder};
+use crate::input::{hints, tick, Command};
+use crate::layout::View;
+use crate::locale::tr;
 use crate::terminal::running_color;
-use crate::{format::format_duration, input::Command};
-use crossterm::{
-    cursor::{MoveTo, MoveToNextLine},
-    queue,
-    style::{Print, Stylize, Color},
-    terminal::{Clear, ClearType},
-};
-
-const UI_WIDTH: usize = 50;
-
-fn frame_top() -> String {
-    format!("╭{}╮", "─".repeat(UI_WIDTH))
-}
-
-fn frame_bottom() -> String {
-    format!("╰{}╯", "─".repeat(UI_WIDTH))
-}
-
-fn frame_sep() -> String {
-    format!("│{}│", "─".repeat(UI_WIDTH))
-}
-
-fn frame_line(s: &str) -> String {
-    let content = if s.len() >= UI_WIDTH {
-        s[..UI_WIDTH].to_string()
-    } else {
-        format!("{}{}", s, " ".repeat(UI_WIDTH - s.len()))
-    };
-    format!("│{}│", content)
-}
-
-fn center_text(s: &str) -> String {
-    if s.len() >= UI_WIDTH {
-        s[..UI_WIDTH].to_string()
-    } else {
-        let pad = (UI_WIDTH - s.len()) / 2;
-        format!("{}{}{}", " ".repeat(pad), s, " ".repeat(UI_WIDTH - pad - s.len()))
-    }
-}
+use crate::{prelude::*, CounterUI};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Stopwatch {
@@ -74,15 +40,11 @@ impl Stopwatch {
     }
 
     pub fn started(&self) -> bool {
-        if matches!(self.start_time, None) {
-            false
-        } else {
-            true
-        }
+        self.start_time.is_some()
     }
 
     pub fn start(&mut self) {
-        if matches!(self.start_time, None) {
+        if self.start_time.is_none() {
             self.start_time = Some(Instant::now());
         }
     }
@@ -107,87 +69,71 @@ impl Stopwatch {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct StopwatchUI {
     stopwatch: Stopwatch,
+    recorder: Recorder,
+}
+
+impl StopwatchUI {
+    pub fn new(recorder: Recorder) -> Self {
+        Self {
+            recorder,
+            ..Default::default()
+        }
+    }
 }
 
+/// Redraw interval while hundredths of a second are shown
+const PRECISE_TICK: Duration = Duration::from_millis(50);
+
 impl CounterUI for StopwatchUI {
+    fn tick(&self) -> Duration {
+        if settings().subseconds {
+            tick().min(PRECISE_TICK)
+        } else {
+            tick()
+        }
+    }
+
     fn show(&mut self, out: &mut impl Write) -> Result<()> {
         let elapsed = self.stopwatch.elapsed();
         let is_running = self.stopwatch.started();
-        // prepare controls split
-    let controls = "[Q]: quit, [Space]: pause/resume";
-    let parts: Vec<&str> = controls.split(',').map(|s| s.trim()).collect();
-    let mid = (parts.len() + 1) / 2;
-    let controls1 = parts[..mid].join(", ");
-    let controls2 = parts[mid..].join(", ");
-    let len1 = controls1.len();
-    let len2 = controls2.len();
-    let styled_controls1 = controls1.clone().with(Color::DarkGrey);
-    let styled_controls2 = controls2.clone().with(Color::DarkGrey);
-
-        let time_raw = format_duration(elapsed);
-        let styled_time = time_raw.clone().with(running_color(is_running));
-
-        queue!(
-            out,
-            MoveTo(0, 0),
-            Print(frame_top()),
-            Clear(ClearType::UntilNewLine),
-            MoveToNextLine(1),
-            // title
-            Print("│"),
-            Print(center_text("Stopwatch").with(Color::Cyan)),
-            Print("│"),
-            Clear(ClearType::UntilNewLine),
-            MoveToNextLine(1),
-            Print(frame_sep()),
-            Clear(ClearType::UntilNewLine),
-            MoveToNextLine(1),
-            // blank framed line above time for symmetry
-            Print(frame_line("")),
-            Clear(ClearType::UntilNewLine),
-            MoveToNextLine(1),
-            // centered time
-            Print("│"),
-            Print(" ".repeat((UI_WIDTH.saturating_sub(time_raw.len()))/2)),
-            Print(styled_time),
-            Print(" ".repeat(UI_WIDTH.saturating_sub(time_raw.len()) - (UI_WIDTH.saturating_sub(time_raw.len()))/2)),
-            Print("│"),
-            Clear(ClearType::UntilNewLine),
-            MoveToNextLine(1),
-            // blank separator
-            Print(frame_line("")),
-            Clear(ClearType::UntilNewLine),
-            MoveToNextLine(1),
-            // controls with uncolored borders
-            Print("│"),
-            Print(styled_controls1),
-            Print(" ".repeat(UI_WIDTH.saturating_sub(len1))),
-            Print("│"),
-            Clear(ClearType::UntilNewLine),
-            MoveToNextLine(1),
-            Print("│"),
-            Print(styled_controls2),
-            Print(" ".repeat(UI_WIDTH.saturating_sub(len2))),
-            Print("│"),
-            Clear(ClearType::UntilNewLine),
-            MoveToNextLine(1),
-            Print(frame_bottom()),
-            Clear(ClearType::FromCursorDown),
-        )?;
-        out.flush()?;
-        Ok(())
+        let style = &settings().time_style;
+        let time_raw = if settings().subseconds {
+            style.precise(elapsed)
+        } else {
+            style.format(elapsed)
+        };
+
+        View {
+            title: tr("stopwatch"),
+            title_color: Some(settings().theme.title),
+            clock: Some((time_raw, running_color(is_running))),
+            paused: !is_running,
+            controls: hints(&[
+                (&[Command::Quit], "action-quit"),
+                (&[Command::Toggle], "action-pause"),
+            ]),
+            ..Default::default()
+        }
+        .draw(out)
     }
 
-    fn update(&mut self, command: Command) {
+    fn update(&mut self, command: Command) -> Result<()> {
         match command {
             Command::Pause => self.stopwatch.stop(),
             Command::Resume => self.stopwatch.start(),
             Command::Toggle | Command::Enter => self.stopwatch.toggle(),
             _ => (),
         }
+        Ok(())
     }
-}
 
+    fn quit(mut self) -> Result<String> {
+        let elapsed = self.stopwatch.elapsed();
+        self.recorder
+            .record(RecordMode::Stopwatch, None, None, None, elapsed, false);
+        Ok(String::new())
+    }
+}
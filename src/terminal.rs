@@ -1,3 +1,4 @@
+use crate::config::settings;
 use crate::{error::PorsmoError, prelude::*};
 use crossterm::{
     cursor::{Hide, MoveTo, Show},
@@ -8,7 +9,7 @@ use crossterm::{
         LeaveAlternateScreen,
     },
 };
-use std::io::{stdout, Stdout};
+use std::io::{stdout, IsTerminal, Stdout};
 
 pub struct TerminalHandler(Stdout);
 
@@ -43,8 +44,39 @@ impl Drop for TerminalHandler {
 }
 
 pub fn running_color(running: bool) -> Color {
-    match running {
-        true => Color::Green,
-        false => Color::Red,
+    if running {
+        settings().theme.running
+    } else {
+        settings().theme.paused
     }
 }
+
+/// Named colors as understood by crossterm (`dark_green`) or `#rrggbb`
+pub fn parse_color(name: &str) -> Result<Color> {
+    let unknown = || PorsmoError::UnknownColor(name.to_string());
+    match name.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| unknown());
+            Ok(Color::Rgb {
+                r: channel(0)?,
+                g: channel(2)?,
+                b: channel(4)?,
+            })
+        }
+        _ => Color::try_from(name.replace('-', "_").as_str()).map_err(|_| unknown()),
+    }
+}
+
+/// Color of a heatmap cell, `level` ranges from 0 (nothing) to 4 (busiest)
+pub fn heat_color(level: u8) -> Color {
+    let heat = &settings().theme.heat;
+    heat[usize::from(level).min(heat.len() - 1)]
+}
+
+/// Whether stdout looks like a terminal that understands color escapes
+/// and `NO_COLOR` isn't set
+pub fn supports_color() -> bool {
+    settings().color
+        && stdout().is_terminal()
+        && std::env::var("TERM").is_ok_and(|term| term != "dumb")
+}
@@ -0,0 +1,419 @@
+use crate::prelude::*;
+use crate::terminal::parse_color;
+use crossterm::style::Color;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Colors of the counter screens
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub title: Color,
+    /// Secondary text such as the end time
+    pub detail: Color,
+    pub controls: Color,
+    pub percent: Color,
+    /// Unfilled part of the progress bar
+    pub bar: Color,
+    pub running: Color,
+    pub paused: Color,
+    /// Notices such as "terminal too small" or a failed sound
+    pub notice: Color,
+    /// Prompts to skip to a work phase, a break or another phase that has
+    /// no color of its own
+    pub work_prompt: Color,
+    pub break_prompt: Color,
+    pub other_prompt: Color,
+    /// Heatmap cells from no pomodoros to the busiest day
+    pub heat: [Color; 5],
+    /// Progress bar colors from start to end on truecolor terminals
+    pub gradient: Option<[Color; 2]>,
+}
+
+const fn rgb(hex: u32) -> Color {
+    Color::Rgb {
+        r: (hex >> 16) as u8,
+        g: (hex >> 8) as u8,
+        b: hex as u8,
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            title: Color::Cyan,
+            detail: Color::DarkGrey,
+            controls: Color::DarkGrey,
+            percent: Color::White,
+            bar: Color::DarkGrey,
+            running: Color::Green,
+            paused: Color::Red,
+            notice: Color::Yellow,
+            work_prompt: Color::Red,
+            break_prompt: Color::Green,
+            other_prompt: Color::Blue,
+            heat: [
+                Color::DarkGrey,
+                Color::AnsiValue(22),
+                Color::AnsiValue(28),
+                Color::AnsiValue(34),
+                Color::AnsiValue(46),
+            ],
+            gradient: None,
+        }
+    }
+}
+
+impl FromStr for Theme {
+    type Err = PorsmoError;
+
+    /// One of the built-in themes: default, mono, colorblind, nord or gruvbox
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "default" => Ok(Self::default()),
+            "mono" => Ok(Self {
+                title: Color::White,
+                detail: Color::Grey,
+                controls: Color::Grey,
+                percent: Color::White,
+                bar: Color::DarkGrey,
+                running: Color::White,
+                paused: Color::DarkGrey,
+                notice: Color::White,
+                work_prompt: Color::White,
+                break_prompt: Color::Grey,
+                other_prompt: Color::Grey,
+                heat: [
+                    Color::DarkGrey,
+                    Color::AnsiValue(241),
+                    Color::AnsiValue(246),
+                    Color::AnsiValue(251),
+                    Color::White,
+                ],
+                gradient: None,
+            }),
+            // blue and orange stay apart with every kind of color blindness,
+            // the heatmap only gets lighter
+            "colorblind" => Ok(Self {
+                running: Color::AnsiValue(39),
+                paused: Color::AnsiValue(214),
+                work_prompt: Color::AnsiValue(214),
+                break_prompt: Color::AnsiValue(39),
+                other_prompt: Color::White,
+                heat: [
+                    Color::DarkGrey,
+                    Color::AnsiValue(18),
+                    Color::AnsiValue(26),
+                    Color::AnsiValue(33),
+                    Color::AnsiValue(117),
+                ],
+                ..Self::default()
+            }),
+            "nord" => Ok(Self {
+                title: rgb(0x88c0d0),
+                detail: rgb(0x4c566a),
+                controls: rgb(0x616e88),
+                percent: rgb(0xeceff4),
+                bar: rgb(0x3b4252),
+                running: rgb(0xa3be8c),
+                paused: rgb(0xbf616a),
+                notice: rgb(0xebcb8b),
+                work_prompt: rgb(0xbf616a),
+                break_prompt: rgb(0xa3be8c),
+                other_prompt: rgb(0x81a1c1),
+                heat: [
+                    rgb(0x3b4252),
+                    rgb(0x53644f),
+                    rgb(0x6b8460),
+                    rgb(0x87a176),
+                    rgb(0xa3be8c),
+                ],
+                gradient: Some([rgb(0xa3be8c), rgb(0xbf616a)]),
+            }),
+            "gruvbox" => Ok(Self {
+                title: rgb(0x83a598),
+                detail: rgb(0x928374),
+                controls: rgb(0x7c6f64),
+                percent: rgb(0xebdbb2),
+                bar: rgb(0x504945),
+                running: rgb(0xb8bb26),
+                paused: rgb(0xfb4934),
+                notice: rgb(0xfabd2f),
+                work_prompt: rgb(0xfb4934),
+                break_prompt: rgb(0xb8bb26),
+                other_prompt: rgb(0x83a598),
+                heat: [
+                    rgb(0x3c3836),
+                    rgb(0x5a5d1a),
+                    rgb(0x79740e),
+                    rgb(0x98971a),
+                    rgb(0xb8bb26),
+                ],
+                gradient: Some([rgb(0xb8bb26), rgb(0xfb4934)]),
+            }),
+            _ => Err(PorsmoError::UnknownTheme(s.to_string())),
+        }
+    }
+}
+
+impl Theme {
+    /// Color `t` of the way along the gradient, `None` without a gradient
+    /// or when either end isn't an RGB color
+    pub fn gradient_at(&self, t: f64) -> Option<Color> {
+        let [Color::Rgb {
+            r: r1,
+            g: g1,
+            b: b1,
+        }, Color::Rgb {
+            r: r2,
+            g: g2,
+            b: b2,
+        }] = self.gradient?
+        else {
+            return None;
+        };
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        Some(Color::Rgb {
+            r: mix(r1, r2),
+            g: mix(g1, g2),
+            b: mix(b1, b2),
+        })
+    }
+}
+
+/// A custom theme from the config, unset colors come from `base`
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ThemeSection {
+    /// Built-in theme to start from, `default` if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub controls: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bar: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub running: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paused: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notice: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub work_prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub break_prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub other_prompt: Option<String>,
+    /// Five heatmap colors from no pomodoros to the busiest day
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heat: Option<[String; 5]>,
+    /// Two `#rrggbb` colors the progress bar fades between
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gradient: Option<[String; 2]>,
+}
+
+impl ThemeSection {
+    pub fn theme(&self) -> Result<Theme> {
+        let mut theme: Theme = self.base.as_deref().unwrap_or("default").parse()?;
+        let colors = [
+            (&self.title, &mut theme.title),
+            (&self.detail, &mut theme.detail),
+            (&self.controls, &mut theme.controls),
+            (&self.percent, &mut theme.percent),
+            (&self.bar, &mut theme.bar),
+            (&self.running, &mut theme.running),
+            (&self.paused, &mut theme.paused),
+            (&self.notice, &mut theme.notice),
+            (&self.work_prompt, &mut theme.work_prompt),
+            (&self.break_prompt, &mut theme.break_prompt),
+            (&self.other_prompt, &mut theme.other_prompt),
+        ];
+        for (name, color) in colors {
+            if let Some(name) = name {
+                *color = parse_color(name)?;
+            }
+        }
+        if let Some(heat) = &self.heat {
+            for (name, color) in heat.iter().zip(&mut theme.heat) {
+                *color = parse_color(name)?;
+            }
+        }
+        if let Some([from, to]) = &self.gradient {
+            theme.gradient = Some([parse_color(from)?, parse_color(to)?]);
+        }
+        Ok(theme)
+    }
+}
+
+/// Characters the frames are drawn with
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Borders {
+    #[default]
+    Rounded,
+    Square,
+    /// Plain ASCII for terminals and fonts without box drawing characters
+    Ascii,
+}
+
+impl Borders {
+    /// Corners as top left, top right, bottom left and bottom right
+    pub fn corners(&self) -> [char; 4] {
+        match self {
+            Self::Rounded => ['╭', '╮', '╰', '╯'],
+            Self::Square => ['┌', '┐', '└', '┘'],
+            Self::Ascii => ['+', '+', '+', '+'],
+        }
+    }
+
+    pub fn horizontal(&self) -> char {
+        match self {
+            Self::Ascii => '-',
+            _ => '─',
+        }
+    }
+
+    pub fn vertical(&self) -> char {
+        match self {
+            Self::Ascii => '|',
+            _ => '│',
+        }
+    }
+
+    /// Marks a running clock
+    pub fn play(&self) -> &'static str {
+        match self {
+            Self::Ascii => ">",
+            _ => "▶",
+        }
+    }
+
+    /// Marks a paused clock
+    pub fn pause(&self) -> &'static str {
+        match self {
+            Self::Ascii => "||",
+            _ => "⏸",
+        }
+    }
+
+    /// Ends text that was cut short
+    pub fn ellipsis(&self) -> &'static str {
+        match self {
+            Self::Ascii => "...",
+            _ => "…",
+        }
+    }
+
+    /// Filled cells of the progress bar and big digits
+    pub fn block(&self) -> char {
+        match self {
+            Self::Ascii => '#',
+            _ => '█',
+        }
+    }
+}
+
+/// Whether color output is wanted, see <https://no-color.org>
+pub fn color_enabled() -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// Whether the terminal advertises 24-bit color
+pub fn truecolor() -> bool {
+    std::env::var("COLORTERM").is_ok_and(|value| value == "truecolor" || value == "24bit")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_themes_parse_by_name() {
+        for name in ["default", "mono", "colorblind", "nord", "gruvbox"] {
+            assert!(name.parse::<Theme>().is_ok(), "{name}");
+        }
+        assert!(matches!(
+            "solarized".parse::<Theme>(),
+            Err(PorsmoError::UnknownTheme(name)) if name == "solarized"
+        ));
+    }
+
+    #[test]
+    fn custom_themes_override_their_base() {
+        let section: ThemeSection = toml::from_str(
+            r##"
+            base = "mono"
+            title = "#ff8000"
+            paused = "dark-red"
+            gradient = ["#000000", "#ffffff"]
+            "##,
+        )
+        .unwrap();
+        let theme = section.theme().unwrap();
+        let mono: Theme = "mono".parse().unwrap();
+        assert_eq!(theme.title, rgb(0xff8000));
+        assert_eq!(theme.paused, Color::DarkRed);
+        assert_eq!(theme.running, mono.running);
+        assert_eq!(theme.gradient, Some([rgb(0), rgb(0xffffff)]));
+
+        for bad in [
+            r##"title = "#ff80""##,
+            r#"base = "nope""#,
+            r#"bar = "purple""#,
+        ] {
+            let section: ThemeSection = toml::from_str(bad).unwrap();
+            assert!(section.theme().is_err(), "{bad}");
+        }
+        assert!(toml::from_str::<ThemeSection>(r#"colour = "red""#).is_err());
+    }
+
+    #[test]
+    fn gradients_mix_between_their_ends() {
+        let theme = Theme {
+            gradient: Some([rgb(0x000000), rgb(0xff8040)]),
+            ..Theme::default()
+        };
+        assert_eq!(theme.gradient_at(-1.0), Some(rgb(0x000000)));
+        assert_eq!(theme.gradient_at(0.5), Some(rgb(0x804020)));
+        assert_eq!(theme.gradient_at(2.0), Some(rgb(0xff8040)));
+        let named = Theme {
+            gradient: Some([Color::Red, rgb(0xffffff)]),
+            ..Theme::default()
+        };
+        assert_eq!(named.gradient_at(0.5), None);
+        assert_eq!(Theme::default().gradient_at(0.5), None);
+    }
+
+    #[test]
+    fn ascii_borders_are_ascii() {
+        let borders = Borders::Ascii;
+        let mut text: String = borders.corners().iter().collect();
+        text.extend([borders.horizontal(), borders.vertical(), borders.block()]);
+        text.extend([borders.play(), borders.pause(), borders.ellipsis()]);
+        assert!(text.is_ascii(), "{text}");
+    }
+
+    #[test]
+    fn the_colorblind_theme_has_no_red_green_cues() {
+        let theme: Theme = "colorblind".parse().unwrap();
+        let red_green = [Color::Red, Color::DarkRed, Color::Green, Color::DarkGreen];
+        let cues = [
+            theme.running,
+            theme.paused,
+            theme.work_prompt,
+            theme.break_prompt,
+        ];
+        for color in cues.iter().chain(&theme.heat) {
+            assert!(!red_green.contains(color), "{color:?}");
+        }
+        assert_ne!(theme.running, theme.paused);
+        assert_ne!(theme.work_prompt, theme.break_prompt);
+        assert_ne!(theme.heat, Theme::default().heat);
+    }
+}
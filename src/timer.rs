@@ -1,192 +1,85 @@
-use crate::alert::Alerter;
+use crate::alert::{AlertKind, Alerter};
+use crate::config::settings;
+use crate::format::{display_duration, TimerTarget};
+use crate::history::{RecordMode, Recorder};
+use crate::input::{hints, Command};
+use crate::layout::View;
+use crate::locale::{tr, tr_with, weekday};
 use crate::stopwatch::Stopwatch;
 use crate::terminal::running_color;
-use crate::{format::format_duration, input::Command};
 use crate::{prelude::*, CounterUI};
-use crossterm::terminal::{Clear, ClearType};
-use crossterm::{
-    cursor::{MoveTo, MoveToNextLine},
-    queue,
-    style::{Print, Stylize, Color},
-};
+use chrono::{DateTime, Datelike, Local};
 use std::io::Write;
 use std::time::Duration;
 
-#[allow(dead_code)]
-fn progress_bar(elapsed: Duration, target: Duration, width: usize) -> String {
-    let ratio = if target.is_zero() {
-        1.0
-    } else {
-        (elapsed.as_secs_f64() / target.as_secs_f64()).clamp(0.0, 1.0)
-    };
-    let filled = (ratio * width as f64).round() as usize;
-    let filled = filled.min(width);
-    let empty = width - filled;
-    let percent = (ratio * 100.0).round() as usize;
-    let bar = format!(
-        "[{}{}] {percent:>3}%",
-        "█".repeat(filled),
-        "-".repeat(empty),
-    );
-    bar
-}
-
-const UI_WIDTH: usize = 50;
-
-fn frame_top() -> String {
-    format!("╭{}╮", "─".repeat(UI_WIDTH))
-}
-
-fn frame_bottom() -> String {
-    format!("╰{}╯", "─".repeat(UI_WIDTH))
-}
-
-fn frame_sep() -> String {
-    format!("│{}│", "─".repeat(UI_WIDTH))
+fn offset(dur: Duration) -> chrono::Duration {
+    chrono::Duration::from_std(dur).unwrap_or_default()
 }
 
-fn frame_line(s: &str) -> String {
-    let content = if s.len() >= UI_WIDTH {
-        s[..UI_WIDTH].to_string()
+/// When the timer ends (or ended) in local time, with the day if not today
+fn end_label(end: DateTime<Local>, ended: bool) -> String {
+    let now = Local::now();
+    let key = if ended { "ended-at" } else { "ends-at" };
+    let time = if end.date_naive() == now.date_naive() {
+        end.format("%H:%M").to_string()
     } else {
-        format!("{}{}", s, " ".repeat(UI_WIDTH - s.len()))
+        let day = weekday(end.weekday().num_days_from_monday());
+        format!("{day} {}", end.format("%H:%M"))
     };
-    format!("│{}│", content)
-}
-
-fn center_text(s: &str) -> String {
-    if s.len() >= UI_WIDTH {
-        s[..UI_WIDTH].to_string()
-    } else {
-        let pad = (UI_WIDTH - s.len()) / 2;
-        format!("{}{}{}", " ".repeat(pad), s, " ".repeat(UI_WIDTH - pad - s.len()))
-    }
+    tr_with(key, &[("time", &time)])
 }
 
 fn timer_show(
     out: &mut impl Write,
     elapsed: Duration,
     target: Duration,
+    end: DateTime<Local>,
     is_running: bool,
+    pausable: bool,
     alerter: &mut Alerter,
 ) -> Result<()> {
-    let (title, timer_raw, controls) = if elapsed < target {
+    let mut controls: Vec<(&[Command], &'static str)> = vec![(&[Command::Quit], "action-quit")];
+    if pausable {
+        controls.push((&[Command::Toggle], "action-pause"));
+    }
+    controls.push((&[Command::VolumeUp, Command::VolumeDown], "action-volume"));
+    let (title, timer_raw) = if elapsed < target {
         let time_left = target.saturating_sub(elapsed);
-        (
-            "Timer",
-            format_duration(time_left),
-            "[Q]: quit, [Space]: pause/resume",
-        )
+        alerter.warn_once(tr("timer"), time_left, target);
+        let time_left = settings().time_style.countdown(time_left);
+        (tr("timer"), time_left)
     } else {
-        alerter.alert_once(
-            "The timer has ended!",
-            format!(
-                "Your Timer of {initial} has ended",
-                initial = format_duration(target)
+        alerter.alarm(
+            tr("timer-alert-title"),
+            tr_with(
+                "timer-alert-message",
+                &[("duration", &display_duration(target))],
             ),
+            AlertKind::TimerEnd,
         );
-        let excess_time = format_duration(elapsed.saturating_sub(target));
-        (
-            "Timer has ended",
-            format!("+{excess_time}"),
-            "[Q]: quit, [Space]: pause/resume",
-        )
+        let excess_time = display_duration(elapsed.saturating_sub(target));
+        let excess_time = format!("+{excess_time}");
+        controls.push((&[Command::Acknowledge], "action-silence"));
+        (tr("timer-ended"), excess_time)
     };
-    // prepare styled time and padding before moving values into the queue
-    let styled_timer = timer_raw.clone().with(running_color(is_running));
-    let _timer_pad = UI_WIDTH.saturating_sub(timer_raw.len());
-    // compute progress geometry
-    let bar_width = 30usize;
     let ratio = if target.is_zero() {
         1.0
     } else {
-        (elapsed.as_secs_f64() / target.as_secs_f64()).clamp(0.0, 1.0)
+        elapsed.as_secs_f64() / target.as_secs_f64()
     };
-    let filled = (ratio * bar_width as f64).round() as usize;
-    let empty = bar_width.saturating_sub(filled);
-    let percent = (ratio * 100.0).round() as usize;
-    let percent_str = format!("{percent:>3}%");
-    // content length = 1('[')+bar_width+1(']')+1(space)+percent_len
-    let content_len = 1 + bar_width + 1 + 1 + percent_str.len();
-    let pad_left = (UI_WIDTH.saturating_sub(content_len)) / 2;
-    let pad_right = UI_WIDTH.saturating_sub(content_len + pad_left);
+    let color = running_color(is_running);
 
-    // split controls defensively (timer controls short, but keep consistency)
-    let parts: Vec<&str> = controls.split(',').map(|s| s.trim()).collect();
-    let mid = (parts.len() + 1) / 2;
-    let controls1 = parts[..mid].join(", ");
-    let controls2 = parts[mid..].join(", ");
-    let controls1_len = controls1.len();
-    let controls2_len = controls2.len();
-
-    queue!(
-        out,
-        MoveTo(0, 0),
-        Print(frame_top()),
-        Clear(ClearType::UntilNewLine),
-    MoveToNextLine(1),
-    // title: print frame borders separately so only content colored
-    Print("│"),
-    Print(center_text(title).with(Color::Cyan)),
-    Print("│"),
-        Clear(ClearType::UntilNewLine),
-        MoveToNextLine(1),
-        Print(frame_sep()),
-    Clear(ClearType::UntilNewLine),
-    MoveToNextLine(1),
-    // blank framed line above time for symmetry
-    Print(frame_line("")),
-    Clear(ClearType::UntilNewLine),
-    MoveToNextLine(1),
-    // timer centered
-    Print("│"),
-    Print(" ".repeat((UI_WIDTH.saturating_sub(timer_raw.len()))/2)),
-    Print(styled_timer),
-    Print(" ".repeat(UI_WIDTH.saturating_sub(timer_raw.len()) - (UI_WIDTH.saturating_sub(timer_raw.len()))/2)),
-    Print("│"),
-        Clear(ClearType::UntilNewLine),
-    MoveToNextLine(1),
-    // blank framed separator for symmetry
-    Print(frame_line("")),
-    Clear(ClearType::UntilNewLine),
-    MoveToNextLine(1),
-    // progress bar
-    Print("│"),
-    Print(" ".repeat(pad_left)),
-    Print("["),
-    Print("█".repeat(filled).with(running_color(is_running))),
-    Print("-".repeat(empty).with(Color::DarkGrey)),
-    Print("] "),
-    Print(percent_str.with(Color::White)),
-    Print(" ".repeat(pad_right)),
-    Print("│"),
-    Clear(ClearType::UntilNewLine),
-    MoveToNextLine(1),
-    // blank framed separator for spacing
-    Print(frame_line("")),
-    Clear(ClearType::UntilNewLine),
-    MoveToNextLine(1),
-    Print(frame_sep()),
-    Clear(ClearType::UntilNewLine),
-    MoveToNextLine(1),
-    // controls split and printed with uncolored borders
-    Print("│"),
-    Print(controls1.clone().with(Color::DarkGrey)),
-    Print(" ".repeat(UI_WIDTH.saturating_sub(controls1_len))),
-    Print("│"),
-    Clear(ClearType::UntilNewLine),
-    MoveToNextLine(1),
-    Print("│"),
-    Print(controls2.clone().with(Color::DarkGrey)),
-    Print(" ".repeat(UI_WIDTH.saturating_sub(controls2_len))),
-    Print("│"),
-        Clear(ClearType::UntilNewLine),
-        MoveToNextLine(1),
-        Print(frame_bottom()),
-        Clear(ClearType::FromCursorDown),
-    )?;
-    out.flush()?;
-    Ok(())
+    View {
+        title,
+        title_color: Some(settings().theme.title),
+        clock: Some((timer_raw, color)),
+        paused: !is_running,
+        detail: Some(end_label(end, elapsed >= target)),
+        progress: Some((ratio, color)),
+        controls: hints(&controls),
+        ..Default::default()
+    }
+    .draw(out)
 }
 
 fn timer_update(command: Command, stopwatch: &mut Stopwatch) {
@@ -199,30 +92,100 @@ fn timer_update(command: Command, stopwatch: &mut Stopwatch) {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone)]
 pub struct TimerUI {
     stopwatch: Stopwatch,
     target: Duration,
+    /// The wall-clock time counted down to, such timers can't be paused
+    /// or reset
+    deadline: Option<DateTime<Local>>,
     alerter: Alerter,
+    recorder: Recorder,
 }
 
 impl TimerUI {
-    pub fn new(target: Duration) -> Self {
+    pub fn new(target: TimerTarget, recorder: Recorder) -> Self {
+        let deadline = match target {
+            TimerTarget::In(_) => None,
+            TimerTarget::At(time) => Some(time),
+        };
         Self {
-            target,
+            target: target.remaining(Local::now()),
+            deadline,
+            recorder,
             ..Default::default()
         }
     }
+
+    /// How far the countdown is, for a wall-clock target the time since it
+    /// started
+    fn elapsed(&self) -> Duration {
+        let Some(deadline) = self.deadline else {
+            return self.stopwatch.elapsed();
+        };
+        let now = Local::now();
+        match (deadline - now).to_std() {
+            Ok(left) => self.target.saturating_sub(left),
+            Err(_) => self.target + (now - deadline).to_std().unwrap_or_default(),
+        }
+    }
+
+    /// When the countdown ends, the given time for a wall-clock target
+    fn end(&self, elapsed: Duration) -> DateTime<Local> {
+        let now = Local::now();
+        match self.deadline {
+            Some(deadline) => deadline,
+            None if elapsed < self.target => now + offset(self.target - elapsed),
+            None => now - offset(elapsed - self.target),
+        }
+    }
 }
 
 impl CounterUI for TimerUI {
     fn show(&mut self, out: &mut impl Write) -> Result<()> {
-        let elapsed = self.stopwatch.elapsed();
+        let elapsed = self.elapsed();
+        let end = self.end(elapsed);
         let is_running = self.stopwatch.started();
-        timer_show(out, elapsed, self.target, is_running, &mut self.alerter)
+        timer_show(
+            out,
+            elapsed,
+            self.target,
+            end,
+            is_running,
+            self.deadline.is_none(),
+            &mut self.alerter,
+        )
+    }
+
+    fn update(&mut self, command: Command) -> Result<()> {
+        if self.deadline.is_some() {
+            if command == Command::Acknowledge {
+                self.alerter.acknowledge();
+            }
+            return Ok(());
+        }
+        match command {
+            Command::Reset => {
+                self.recorder.restart();
+                self.alerter.reset();
+            }
+            Command::Acknowledge => self.alerter.acknowledge(),
+            _ => (),
+        }
+        timer_update(command, &mut self.stopwatch);
+        Ok(())
     }
 
-    fn update(&mut self, command: Command) {
-        timer_update(command, &mut self.stopwatch)
+    fn quit(mut self) -> Result<String> {
+        let elapsed = self.elapsed();
+        self.recorder.record(
+            RecordMode::Timer,
+            None,
+            None,
+            Some(self.target),
+            elapsed,
+            false,
+        );
+        Ok(String::new())
     }
 }